@@ -1,6 +1,6 @@
 //! Package data from the Arch Linux website.
 
-use alpm::Package as AlpmPackage;
+use alpm::Pkg as AlpmPackage;
 use bytesize::ByteSize;
 use chrono::NaiveDateTime;
 use colored::*;
@@ -10,6 +10,9 @@ use std::fmt;
 /// JSON endpoint to use for searching packages.
 pub const ARCHWEB_ENDPOINT: &str = "https://archlinux.org/packages/search/json";
 
+/// Name of the local package database.
+const LOCAL_DB_NAME: &str = "local";
+
 /// Search result from archlinux.org
 #[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -64,6 +67,13 @@ impl<'a> From<AlpmPackage<'a>> for ArchwebPackage {
     fn from(pkg: AlpmPackage<'a>) -> Self {
         Self {
             pkgname: pkg.name().to_string(),
+            pkgbase: pkg.base().unwrap_or_else(|| pkg.name()).to_string(),
+            repo: pkg
+                .db()
+                .map(|db| db.name())
+                .filter(|name| *name != LOCAL_DB_NAME)
+                .unwrap_or_default()
+                .to_string(),
             pkgver: pkg.version().as_str().to_string(),
             arch: pkg.arch().unwrap_or("-").to_string(),
             pkgdesc: pkg.desc().unwrap_or("-").to_string(),
@@ -78,12 +88,13 @@ impl<'a> From<AlpmPackage<'a>> for ArchwebPackage {
     }
 }
 
-impl fmt::Display for ArchwebPackage {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(&format!("\t{:16}: {}\n", "Name".cyan(), self.pkgname))?;
-        f.write_str(&format!(
-            "\t{:16}: {}{}{}\n",
-            "Version".cyan(),
+impl ArchwebPackage {
+    /// Returns the full version of the package in `[epoch:]pkgver-pkgrel` format.
+    ///
+    /// Local packages contain the full version in `pkgver`.
+    pub fn version(&self) -> String {
+        format!(
+            "{}{}{}",
             if self.epoch != 0 {
                 format!("{}:", self.epoch)
             } else {
@@ -95,7 +106,28 @@ impl fmt::Display for ArchwebPackage {
             } else {
                 String::new()
             }
-        ))?;
+        )
+    }
+
+    /// Returns the URL of the package page on archlinux.org
+    ///
+    /// The URL is not available for packages without a repository.
+    pub fn package_url(&self) -> Option<String> {
+        if self.repo.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "https://archlinux.org/packages/{}/{}/{}/",
+                self.repo, self.arch, self.pkgbase
+            ))
+        }
+    }
+}
+
+impl fmt::Display for ArchwebPackage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format!("\t{:16}: {}\n", "Name".cyan(), self.pkgname))?;
+        f.write_str(&format!("\t{:16}: {}\n", "Version".cyan(), self.version()))?;
         f.write_str(&format!("\t{:16}: {}\n", "Architecture".cyan(), self.arch))?;
         if !self.repo.is_empty() {
             f.write_str(&format!("\t{:16}: {}\n", "Repository".cyan(), self.repo))?;
@@ -150,14 +182,8 @@ impl fmt::Display for ArchwebPackage {
         if let Some(date) = &self.flag_date {
             f.write_str(&format!("\t{:16}: {}\n", "Flag Date".red(), date))?;
         }
-        if !self.repo.is_empty() {
-            f.write_str(&format!(
-                "\t{:16}: https://archlinux.org/packages/{}/{}/{}/\n",
-                "Package URL".cyan(),
-                self.repo,
-                self.arch,
-                self.pkgbase
-            ))?;
+        if let Some(url) = self.package_url() {
+            f.write_str(&format!("\t{:16}: {}\n", "Package URL".cyan(), url))?;
         }
         Ok(())
     }
@@ -1,9 +1,20 @@
 //! Command-line argument parser.
 
-use clap::{AppSettings, Parser};
-use rebuilderd_common::Status;
+use crate::config::{self, Config, Settings};
+use crate::error::ReproStatusError;
+use crate::history;
+use crate::package::{self, Policy, Status};
+use crate::stats::StatsGroup;
+use bytesize::ByteSize;
+use clap::{
+    AppSettings, ArgEnum, ArgGroup, Command, CommandFactory, FromArgMatches, Parser, Subcommand,
+};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// Address of the default rebuilderd instance.
+pub const DEFAULT_REBUILDERD: &str = "https://reproducible.archlinux.org";
+
 /// Command-line arguments to parse.
 #[derive(Debug, Parser)]
 #[clap(
@@ -21,54 +32,408 @@ pub struct Args {
     /// Increases the logging verbosity.
     #[clap(short, long, parse(from_occurrences), alias = "debug")]
     pub verbose: u8,
+    /// Sets the path of the configuration file.
+    #[clap(long, value_name = "PATH", env = "ARCH_REPRO_STATUS_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Sets the profile to use from the configuration file.
+    #[clap(long, value_name = "NAME", env = "ARCH_REPRO_STATUS_PROFILE")]
+    pub profile: Option<String>,
     /// Checks all of the packages on the system.
     #[clap(short, long)]
     pub all: bool,
+    /// Checks the targets of a pacman transaction that are read from stdin.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "maintainer", "pkglist", "pkgfile", "inspect", "verify", "require", "attest", "output",
+        ]
+    )]
+    pub hook: bool,
+    /// Returns a failure exit code for the --fail-on targets in hook mode.
+    #[clap(long, requires = "hook")]
+    pub strict: bool,
     /// Sets the username of the maintainer.
     #[clap(short, long, value_name = "MAINTAINER", env)]
     pub maintainer: Option<String>,
-    /// Sets the address of the rebuilderd instance.
+    /// Checks the packages in the given list ("-" for stdin) instead of the installed ones.
+    #[clap(long, value_name = "FILE", conflicts_with = "maintainer")]
+    pub pkglist: Option<PathBuf>,
+    /// Checks the given package files instead of the installed ones.
     #[clap(
-        short,
         long,
-        value_name = "URL",
-        default_value = "https://reproducible.archlinux.org",
-        env
+        value_name = "FILE",
+        multiple_occurrences = true,
+        conflicts_with = "maintainer"
     )]
-    pub rebuilderd: String,
-    /// Sets the path to the pacman database.
+    pub pkgfile: Vec<PathBuf>,
+    /// Sets the address of the rebuilderd instance(s).
     #[clap(
-        short = 'b',
+        short,
         long,
-        value_name = "PATH",
-        default_value = "/var/lib/pacman",
+        value_name = "URL",
+        default_value = DEFAULT_REBUILDERD,
+        multiple_occurrences = true,
+        use_value_delimiter = true,
         env
     )]
-    pub dbpath: String,
-    /// Sets the repositories to query.
+    pub rebuilderd: Vec<String>,
+    /// Sets the policy for combining the results of multiple rebuilderd instances.
     #[clap(
         long,
-        value_name = "REPO",
-        default_value = "core,extra,community,multilib",
-        use_value_delimiter = true
+        value_name = "POLICY",
+        arg_enum,
+        default_value = "all",
+        env = "ARCH_REPRO_STATUS_POLICY"
     )]
-    pub repos: Vec<String>,
+    pub policy: Policy,
+    /// Sets the root directory of the system to check (e.g. a chroot).
+    #[clap(long, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+    /// Sets the path of the pacman configuration file [default: /etc/pacman.conf].
+    #[clap(long, value_name = "PATH", env = "ARCH_REPRO_STATUS_PACMAN_CONF")]
+    pub pacman_conf: Option<PathBuf>,
+    /// Sets the path to the pacman database (overrides pacman.conf).
+    #[clap(short = 'b', long, value_name = "PATH", env)]
+    pub dbpath: Option<String>,
+    /// Sets the repositories to query (overrides pacman.conf).
+    #[clap(long, value_name = "REPO", use_value_delimiter = true)]
+    pub repos: Option<Vec<String>>,
     /// Sets the filter for package status.
     #[clap(
         short,
         long,
         value_name = "STATUS",
-        possible_values = &["GOOD", "BAD", "UNKWN"],
+        possible_values = &["GOOD", "BAD", "UNKWN", "STALE"],
         env
     )]
     pub filter: Option<Status>,
+    /// Requires the given number of rebuilderd instances to report GOOD for each package.
+    #[clap(long, value_name = "N", env = "ARCH_REPRO_STATUS_REQUIRE")]
+    pub require: Option<usize>,
+    /// Sets the trusted set of rebuilderd instances (from the configuration file) to count.
+    #[clap(
+        long,
+        value_name = "NAME",
+        requires = "require",
+        env = "ARCH_REPRO_STATUS_TRUSTED_SET"
+    )]
+    pub trusted_set: Option<String>,
+    /// Trusted sets of rebuilderd instances from the configuration file.
+    #[clap(skip)]
+    pub trusted_sets: HashMap<String, Vec<String>>,
+    /// Sets the package statuses that result in a failure exit code [default: BAD, BAD,UNKWN in hook mode].
+    #[clap(
+        long,
+        value_name = "STATUS",
+        possible_values = &["BAD", "UNKWN", "STALE"],
+        use_value_delimiter = true,
+        env = "ARCH_REPRO_STATUS_FAIL_ON"
+    )]
+    pub fail_on: Vec<Status>,
+    /// Sets the output format.
+    #[clap(
+        short,
+        long,
+        value_name = "FORMAT",
+        arg_enum,
+        default_value = "text",
+        env = "ARCH_REPRO_STATUS_OUTPUT"
+    )]
+    pub output: OutputFormat,
+    /// Sets the columns to include in the CSV/TSV output.
+    #[clap(
+        long,
+        value_name = "COLUMN",
+        arg_enum,
+        default_value = "pkgname,pkgbase,version,repo,arch,status,build_id,packager,build_date,maintainers",
+        use_value_delimiter = true
+    )]
+    pub columns: Vec<Column>,
+    /// Groups the results by the given field.
+    #[clap(
+        long,
+        value_name = "FIELD",
+        arg_enum,
+        env = "ARCH_REPRO_STATUS_GROUP_BY"
+    )]
+    pub group_by: Option<GroupBy>,
     /// Views the build log or diffoscope of the interactively selected package.
     #[clap(short, long)]
     pub inspect: bool,
+    /// Verifies the local package files against the checked artifacts (may download the artifacts).
+    #[clap(long)]
+    pub verify: bool,
+    /// Sets the package cache directory to look for the package files in.
+    #[clap(long, value_name = "DIR", multiple_occurrences = true)]
+    pub pkg_cache: Vec<PathBuf>,
+    /// Verifies the in-toto attestations of the rebuilderd instances.
+    #[clap(long)]
+    pub attest: bool,
+    /// Sets the hex encoded ed25519 public keys of the trusted rebuilders.
+    #[clap(
+        long = "trusted-key",
+        value_name = "KEY",
+        multiple_occurrences = true,
+        use_value_delimiter = true,
+        env = "ARCH_REPRO_STATUS_TRUSTED_KEYS"
+    )]
+    pub trusted_keys: Vec<String>,
     /// Sets the pager for viewing files.
     #[clap(short, long, value_name = "PAGER", default_value = "less", env)]
     pub pager: String,
-    /// Sets the cache directory for log files.
+    /// Sets the cache directory for log files and fetched data.
     #[clap(short, long, value_name = "DIR", env)]
     pub cache_dir: Option<PathBuf>,
+    /// Uses the cached data instead of fetching it.
+    #[clap(long)]
+    pub offline: bool,
+    /// Sets the timeout (in seconds) of the requests [default: 10 in hook mode].
+    #[clap(long, value_name = "SECS", env = "ARCH_REPRO_STATUS_TIMEOUT")]
+    pub timeout: Option<u64>,
+    /// Sets the maximum age (in seconds) of the cached data to use without fetching.
+    #[clap(
+        long,
+        value_name = "SECS",
+        default_value = "0",
+        env = "ARCH_REPRO_STATUS_MAX_AGE"
+    )]
+    pub max_age: u64,
+    /// Sets the maximum total size of the cached logs.
+    #[clap(
+        long,
+        value_name = "SIZE",
+        default_value = "100MiB",
+        env = "ARCH_REPRO_STATUS_CACHE_SIZE"
+    )]
+    pub cache_size: ByteSize,
+    /// Disables recording the results in the history.
+    #[clap(long)]
+    pub no_history: bool,
+    /// Sets the maximum number of history records to keep for each checked set of packages.
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = history::DEFAULT_MAX_RECORDS,
+        env = "ARCH_REPRO_STATUS_HISTORY_SIZE"
+    )]
+    pub history_size: usize,
+    /// Subcommand to run.
+    #[clap(subcommand)]
+    pub command: Option<SubCommand>,
+}
+
+/// Subcommands of the application.
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum SubCommand {
+    /// Reports the regressions, fixes and newly unknown packages since the previous run.
+    #[clap(alias = "history")]
+    Diff {
+        /// Compares with the last run before the given date (YYYY-MM-DD or RFC 3339).
+        #[clap(long, value_name = "DATE", parse(try_from_str = history::parse_date))]
+        since: Option<i64>,
+    },
+    /// Shows the reproducibility percentage over time.
+    Stats {
+        /// Only includes the runs since the given date (YYYY-MM-DD or RFC 3339).
+        #[clap(long, value_name = "DATE", parse(try_from_str = history::parse_date))]
+        since: Option<i64>,
+        /// Groups the results by the checked set of packages, maintainer or repository.
+        #[clap(long, arg_enum, value_name = "GROUP")]
+        by: Option<StatsGroup>,
+    },
+    /// Manages the cached logs and responses.
+    Cache {
+        /// Action to perform.
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+/// Actions of the cache subcommand.
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum CacheCommand {
+    /// Lists the cached entries.
+    List,
+    /// Removes the cached entries that are too old or exceed the total size.
+    #[clap(group(ArgGroup::new("limit").required(true).multiple(true)))]
+    Prune {
+        /// Removes the entries that are older than the given age (in seconds).
+        #[clap(long, value_name = "SECS", group = "limit")]
+        max_age: Option<u64>,
+        /// Removes the oldest entries until the total size is within the given size.
+        #[clap(long, value_name = "SIZE", group = "limit")]
+        max_size: Option<ByteSize>,
+        /// Removes the oldest history records until each checked set of packages has at most N records.
+        #[clap(long, value_name = "N", group = "limit")]
+        max_records: Option<usize>,
+    },
+    /// Removes all of the cached entries.
+    Clear,
+}
+
+impl Args {
+    /// Normalizes the rebuilderd instances and removes the duplicates.
+    ///
+    /// The trusted sets are normalized as well so that each instance is counted once.
+    pub fn dedup_rebuilderd(&mut self) {
+        self.rebuilderd = package::dedup_rebuilderd_urls(&self.rebuilderd);
+        for urls in self.trusted_sets.values_mut() {
+            *urls = package::dedup_rebuilderd_urls(urls);
+        }
+    }
+
+    /// Returns the command with the default values taken from the given settings.
+    ///
+    /// Values that are not in the possible values of an argument are rejected.
+    pub fn command_with_settings(
+        settings: &Settings,
+    ) -> Result<Command<'static>, ReproStatusError> {
+        let mut command = Self::command();
+        for (id, value) in settings.get_defaults() {
+            if let Some(possible_values) = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == id)
+                .and_then(|arg| arg.get_possible_values())
+            {
+                if let Some(invalid) = value.split(',').find(|value| {
+                    !possible_values
+                        .iter()
+                        .any(|possible_value| possible_value.matches(value, false))
+                }) {
+                    return Err(ReproStatusError::ConfigError(format!(
+                        "invalid value for `{}`: `{}`",
+                        id, invalid
+                    )));
+                }
+            }
+            command = command.mut_arg(id, |arg| {
+                arg.default_value(Box::leak(value.into_boxed_str()))
+            });
+        }
+        Ok(command)
+    }
+
+    /// Parses the command-line arguments and applies the configuration file.
+    ///
+    /// The precedence of the values is: arguments > environment variables
+    /// > configuration file > defaults.
+    pub fn parse_with_config() -> Result<Self, ReproStatusError> {
+        let matches = Self::command()
+            .ignore_errors(true)
+            .disable_help_flag(true)
+            .disable_version_flag(true)
+            .get_matches();
+        let profile = matches.value_of("profile");
+        let path = match matches.value_of("config") {
+            Some(path) => PathBuf::from(path),
+            None => match config::get_default_config_path() {
+                Some(path) if path.exists() => path,
+                _ if profile.is_some() => {
+                    return Err(ReproStatusError::ConfigError(String::from(
+                        "configuration file is not found",
+                    )))
+                }
+                _ if matches.value_of("trusted-set").is_some() => {
+                    return Err(ReproStatusError::ConfigError(String::from(
+                        "configuration file is not found (trusted sets are defined in it)",
+                    )))
+                }
+                _ => return Ok(Self::parse()),
+            },
+        };
+        let config = Config::load(&path)?;
+        let trusted_sets = config.trusted_sets.clone();
+        let settings = config.get_settings(profile)?;
+        let mut args =
+            Self::from_arg_matches(&Self::command_with_settings(&settings)?.get_matches())
+                .unwrap_or_else(|e| e.exit());
+        args.trusted_sets = trusted_sets;
+        Ok(args)
+    }
+}
+
+/// Output format of the results.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum OutputFormat {
+    /// Human readable text.
+    Text,
+    /// JSON document.
+    Json,
+    /// Comma-separated values.
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+    /// Self-contained HTML report.
+    Html,
+}
+
+/// Column of the CSV/TSV output.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum Column {
+    /// Package name.
+    #[clap(name = "pkgname")]
+    Pkgname,
+    /// Package base.
+    #[clap(name = "pkgbase")]
+    Pkgbase,
+    /// Full version of the package.
+    #[clap(name = "version")]
+    Version,
+    /// Repository of the package.
+    #[clap(name = "repo")]
+    Repo,
+    /// Architecture of the package.
+    #[clap(name = "arch")]
+    Arch,
+    /// Reproducibility status.
+    #[clap(name = "status")]
+    Status,
+    /// Rebuilderd build ID.
+    #[clap(name = "build_id")]
+    BuildId,
+    /// Last packager.
+    #[clap(name = "packager")]
+    Packager,
+    /// Build date.
+    #[clap(name = "build_date")]
+    BuildDate,
+    /// Maintainers of the package.
+    #[clap(name = "maintainers")]
+    Maintainers,
+    /// Results of each rebuilderd instance.
+    #[clap(name = "results")]
+    Results,
+    /// Result of verifying the local package file.
+    #[clap(name = "verification")]
+    Verification,
+    /// Number of the trusted rebuilders that attested the package.
+    #[clap(name = "attested_by")]
+    AttestedBy,
+    /// Verdict of the threshold policy.
+    #[clap(name = "verdict")]
+    Verdict,
+}
+
+/// Field to group the results by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum GroupBy {
+    /// Repository of the package.
+    Repo,
+    /// Maintainers of the package.
+    Maintainer,
+    /// Last packager.
+    Packager,
+    /// Package base.
+    Pkgbase,
+    /// Architecture of the package.
+    Arch,
+}
+
+impl Column {
+    /// Returns the name of the column to use in the header.
+    pub fn name(&self) -> &'static str {
+        self.to_possible_value()
+            .map(|value| value.get_name())
+            .unwrap_or_default()
+    }
 }
@@ -0,0 +1,225 @@
+//! Verification of the in-toto attestations of rebuilderd.
+
+use crate::error::ReproStatusError;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde_json::Value;
+use std::convert::TryFrom;
+
+/// Signature of an attestation.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct AttestationSignature {
+    /// ID of the signing key.
+    pub keyid: String,
+    /// Hex encoded ed25519 signature.
+    pub sig: String,
+}
+
+/// Signed in-toto link metadata that is published for a build.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Attestation {
+    /// Signatures over the canonical JSON of the signed metadata.
+    pub signatures: Vec<AttestationSignature>,
+    /// Signed metadata.
+    pub signed: Value,
+}
+
+/// Writes the canonical JSON representation of the given value.
+///
+/// The canonical form has sorted keys, no whitespace and only escapes
+/// backslashes and quotes as described in the in-toto specification.
+fn write_canonical_json(value: &Value, output: &mut String) -> Result<(), ReproStatusError> {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => output.push_str(&number.to_string()),
+            None => {
+                return Err(ReproStatusError::AttestationError(format!(
+                    "non-integer number in canonical JSON: {}",
+                    number
+                )))
+            }
+        },
+        Value::String(value) => {
+            output.push('"');
+            for c in value.chars() {
+                if c == '"' || c == '\\' {
+                    output.push('\\');
+                }
+                output.push(c);
+            }
+            output.push('"');
+        }
+        Value::Array(values) => {
+            output.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i != 0 {
+                    output.push(',');
+                }
+                write_canonical_json(value, output)?;
+            }
+            output.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys = map.keys().collect::<Vec<&String>>();
+            keys.sort();
+            output.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i != 0 {
+                    output.push(',');
+                }
+                write_canonical_json(&Value::String(key.to_string()), output)?;
+                output.push(':');
+                write_canonical_json(&map[key], output)?;
+            }
+            output.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Returns the canonical JSON representation of the given value.
+pub fn to_canonical_json(value: &Value) -> Result<String, ReproStatusError> {
+    let mut output = String::new();
+    write_canonical_json(value, &mut output)?;
+    Ok(output)
+}
+
+/// Parses the given hex encoded ed25519 public key.
+pub fn parse_public_key(key: &str) -> Result<VerifyingKey, ReproStatusError> {
+    hex::decode(key.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| ReproStatusError::AttestationError(format!("invalid public key: `{}`", key)))
+}
+
+impl Attestation {
+    /// Returns the SHA-256 checksum of the product with the given file name prefix.
+    ///
+    /// Signature files are ignored.
+    pub fn get_product_checksum(&self, prefix: &str) -> Option<&str> {
+        self.signed
+            .get("products")
+            .and_then(Value::as_object)?
+            .iter()
+            .find(|(path, _)| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                name.starts_with(prefix) && !name.ends_with(".sig")
+            })
+            .and_then(|(_, hashes)| hashes.get("sha256"))
+            .and_then(Value::as_str)
+    }
+
+    /// Checks if the attestation is about the package file with the given
+    /// file name prefix and SHA-256 checksum.
+    pub fn verify_product(&self, prefix: &str, checksum: &str) -> Result<(), ReproStatusError> {
+        match self.get_product_checksum(prefix) {
+            Some(product) if product == checksum => Ok(()),
+            Some(product) => Err(ReproStatusError::AttestationError(format!(
+                "checksum mismatch for `{}`: expected {}, attested {}",
+                prefix, checksum, product
+            ))),
+            None => Err(ReproStatusError::AttestationError(format!(
+                "no product matches `{}`",
+                prefix
+            ))),
+        }
+    }
+
+    /// Returns the indices of the given keys that have a valid signature
+    /// over the signed metadata.
+    ///
+    /// Signatures are verified strictly so that malleable signatures and
+    /// weak (small order) keys are rejected.
+    pub fn get_signers(&self, keys: &[VerifyingKey]) -> Result<Vec<usize>, ReproStatusError> {
+        let message = to_canonical_json(&self.signed)?;
+        let signatures = self
+            .signatures
+            .iter()
+            .filter_map(|signature| hex::decode(&signature.sig).ok())
+            .filter_map(|bytes| Signature::from_slice(&bytes).ok())
+            .collect::<Vec<Signature>>();
+        Ok(keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| {
+                signatures
+                    .iter()
+                    .any(|signature| key.verify_strict(message.as_bytes(), signature).is_ok())
+            })
+            .map(|(i, _)| i)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ed25519_dalek::{Signer, SigningKey};
+    use pretty_assertions::assert_eq;
+
+    /// Returns the signing key with the given seed to use for testing.
+    fn get_test_keypair(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_canonical_json() -> Result<()> {
+        let value = serde_json::from_str(
+            r#"{"b": [1, true, null], "a": "q\"u\\o\nte", "c": {"y": -2, "x": {}}}"#,
+        )?;
+        assert_eq!(
+            "{\"a\":\"q\\\"u\\\\o\nte\",\"b\":[1,true,null],\"c\":{\"x\":{},\"y\":-2}}",
+            to_canonical_json(&value)?
+        );
+        assert!(to_canonical_json(&serde_json::from_str("1.5")?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_attestation() -> Result<()> {
+        let signed = serde_json::from_str::<Value>(
+            r#"{"_type": "link", "name": "rebuild", "materials": {}, "products": {
+                "test-0.1-1-x86_64.pkg.tar.zst": {"sha256": "abc"},
+                "test-0.1-1-x86_64.pkg.tar.zst.sig": {"sha256": "xyz"}
+            }}"#,
+        )?;
+        let keypair = get_test_keypair(1);
+        let signature = keypair.sign(to_canonical_json(&signed)?.as_bytes());
+        let attestation = Attestation {
+            signatures: vec![AttestationSignature {
+                keyid: String::from("test"),
+                sig: hex::encode(signature.to_bytes()),
+            }],
+            signed,
+        };
+        let untrusted = get_test_keypair(2).verifying_key();
+        let trusted = parse_public_key(&hex::encode(keypair.verifying_key().to_bytes()))?;
+        assert_eq!(vec![1], attestation.get_signers(&[untrusted, trusted])?);
+        assert_eq!(Vec::<usize>::new(), attestation.get_signers(&[untrusted])?);
+        assert!(parse_public_key("xyz").is_err());
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let weak = parse_public_key(&hex::encode(identity))?;
+        let forged = Attestation {
+            signatures: vec![AttestationSignature {
+                keyid: String::from("weak"),
+                sig: hex::encode([identity, [0; 32]].concat()),
+            }],
+            signed: attestation.signed.clone(),
+        };
+        assert_eq!(Vec::<usize>::new(), forged.get_signers(&[weak])?);
+        assert!(attestation
+            .verify_product("test-0.1-1-x86_64.pkg.tar", "abc")
+            .is_ok());
+        assert!(attestation
+            .verify_product("test-0.1-1-x86_64.pkg.tar", "xyz")
+            .is_err());
+        assert!(attestation
+            .verify_product("test-0.1-2-x86_64.pkg.tar", "abc")
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,769 @@
+//! Cache for the fetched data.
+
+use crate::error::ReproStatusError;
+use crate::package::LogType;
+use chrono::Utc;
+use std::fs::{self, File};
+use std::io::{self, Error as IoError, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tempfile::NamedTempFile;
+
+/// Returns the cache directory.
+///
+/// Defaults to `$XDG_CACHE_HOME/arch-repro-status` if it is not specified.
+pub fn get_cache_dir(cache_dir: Option<PathBuf>) -> Result<PathBuf, ReproStatusError> {
+    Ok(cache_dir
+        .or_else(|| dirs_next::cache_dir().map(|p| p.join(env!("CARGO_PKG_NAME"))))
+        .ok_or_else(|| IoError::other("cannot find cache directory"))?)
+}
+
+/// Name of the directory that the logs are cached in.
+pub const LOGS_DIR: &str = "logs";
+
+/// Name of the directory that the HTTP responses are cached in.
+pub const HTTP_DIR: &str = "http";
+
+/// Suffix of the cached log files that are compressed with zstd.
+pub const LOG_SUFFIX: &str = ".log.zst";
+
+/// Suffix of the temporary files of the unfinished writes.
+const TEMP_SUFFIX: &str = ".part";
+
+/// Age of a temporary file after which its write is considered interrupted.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(600);
+
+/// Creates a temporary file in the directory of the given path.
+fn create_temp_file(path: &Path) -> Result<NamedTempFile, ReproStatusError> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir)?;
+    let prefix = path
+        .file_name()
+        .map(|name| format!(".{}.", name.to_string_lossy()))
+        .unwrap_or_else(|| String::from("."));
+    Ok(tempfile::Builder::new()
+        .prefix(&prefix)
+        .suffix(TEMP_SUFFIX)
+        .tempfile_in(dir)?)
+}
+
+/// Writes the given contents to a file atomically.
+///
+/// The contents are written to a temporary file which is renamed afterwards,
+/// so an interrupted write never leaves a partial file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), ReproStatusError> {
+    let mut file = create_temp_file(path)?;
+    file.write_all(contents)?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Writer that compresses a log into the cache.
+///
+/// The log is written to a temporary file and appears in the cache
+/// only after it is finished.
+pub struct LogWriter {
+    /// Path of the cached log.
+    path: PathBuf,
+    /// Encoder that writes to the temporary file.
+    encoder: zstd::Encoder<'static, NamedTempFile>,
+}
+
+impl LogWriter {
+    /// Constructs a new instance for the given path of the cached log.
+    pub fn new(path: &Path) -> Result<Self, ReproStatusError> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            encoder: zstd::Encoder::new(create_temp_file(path)?, zstd::DEFAULT_COMPRESSION_LEVEL)?,
+        })
+    }
+
+    /// Finishes the compression and moves the log into the cache.
+    pub fn finish(self) -> Result<(), ReproStatusError> {
+        self.encoder
+            .finish()?
+            .persist(&self.path)
+            .map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Returns a reader of the given cached log that decompresses it.
+pub fn read_log(path: &Path) -> Result<impl Read, ReproStatusError> {
+    Ok(zstd::Decoder::new(File::open(path)?)?)
+}
+
+/// Replaces the characters that are not safe to use in file names.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Returns the name of the cache directory for the given rebuilderd instance.
+///
+/// It consists of the host, the port (if it is not the default one)
+/// and the path (if the instance is not served from the root).
+pub fn get_instance_dir_name(rebuilderd: &str) -> String {
+    match reqwest::Url::parse(rebuilderd) {
+        Ok(url) => {
+            let mut name = match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) => format!("{}_{}", host, port),
+                (Some(host), None) => host.to_string(),
+                _ => return sanitize_file_name(rebuilderd),
+            };
+            let path = url.path().trim_matches('/');
+            if !path.is_empty() {
+                name = format!("{}_{}", name, sanitize_file_name(path));
+            }
+            name
+        }
+        Err(_) if rebuilderd.is_empty() => String::from("unknown"),
+        Err(_) => sanitize_file_name(rebuilderd),
+    }
+}
+
+/// Removes the logs of the previous flat layout (`{build_id}_{log_type}.log`).
+///
+/// They cannot be attributed to a rebuilderd instance and may contain
+/// the bodies of error responses, so they are not reused.
+///
+/// Returns the number of the removed files.
+pub fn remove_legacy_logs(cache_dir: &Path) -> Result<usize, ReproStatusError> {
+    let entries = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+    let mut count = 0;
+    for path in entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+    {
+        let is_log = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".log"))
+            .and_then(|name| name.split_once('_'))
+            .is_some_and(|(build_id, log_type)| {
+                build_id.parse::<i32>().is_ok() && ["build", "diffoscope"].contains(&log_type)
+            });
+        if is_log {
+            fs::remove_file(&path)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Metadata of a cached log file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LogMetadata {
+    /// Name of the package.
+    pub pkgname: String,
+    /// Full version of the package.
+    pub version: String,
+    /// Rebuilderd build ID.
+    pub build_id: i32,
+    /// Type of the log.
+    pub log_type: LogType,
+    /// URL that the log is fetched from.
+    pub url: String,
+    /// Unix timestamp of the time that the log is fetched.
+    pub fetched_at: i64,
+}
+
+impl LogMetadata {
+    /// Returns the path of the metadata file for the given log file.
+    pub fn get_path(log_path: &Path) -> PathBuf {
+        let name = log_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        log_path.with_file_name(format!(
+            "{}.json",
+            name.strip_suffix(LOG_SUFFIX).unwrap_or(&name)
+        ))
+    }
+
+    /// Returns the metadata of the given log file.
+    pub fn read(log_path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(Self::get_path(log_path)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Saves the metadata next to the given log file.
+    pub fn write(&self, log_path: &Path) -> Result<(), ReproStatusError> {
+        write_atomic(
+            &Self::get_path(log_path),
+            serde_json::to_string(self)?.as_bytes(),
+        )
+    }
+}
+
+/// Cached log file or HTTP response.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CacheEntry {
+    /// Path of the cached file.
+    pub path: PathBuf,
+    /// Type of the log (`None` for HTTP responses and temporary files).
+    pub log_type: Option<LogType>,
+    /// Whether if the entry is a temporary file of an interrupted write.
+    pub is_partial: bool,
+    /// Directory name of the rebuilderd instance that the log is fetched from.
+    pub instance: Option<String>,
+    /// Package name and version of the log or the file name of the response.
+    pub name: String,
+    /// Size of the entry (including its metadata) in bytes.
+    pub size: u64,
+    /// Unix timestamp of the last modification.
+    pub modified: i64,
+}
+
+impl CacheEntry {
+    /// Constructs a new instance from the given file.
+    fn new(path: PathBuf, log_type: Option<LogType>) -> Result<Self, ReproStatusError> {
+        let metadata = fs::metadata(&path)?;
+        let log_metadata = log_type.and_then(|_| LogMetadata::read(&path));
+        let metadata_size = match log_type {
+            Some(_) => fs::metadata(LogMetadata::get_path(&path)).map_or(0, |v| v.len()),
+            None => 0,
+        };
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let instance_dir = path
+            .parent()
+            .filter(|dir| dir.parent().and_then(Path::file_name) == Some(LOGS_DIR.as_ref()));
+        Ok(Self {
+            instance: instance_dir
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().to_string()),
+            is_partial: file_name.ends_with(TEMP_SUFFIX),
+            name: match log_metadata {
+                Some(log_metadata) => format!("{} {}", log_metadata.pkgname, log_metadata.version),
+                None => {
+                    let name = file_name.trim_start_matches('.');
+                    match name.split_once('.') {
+                        Some((stem, _)) => stem.to_string(),
+                        None => name.to_string(),
+                    }
+                }
+            },
+            size: metadata.len() + metadata_size,
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_secs() as i64),
+            path,
+            log_type,
+        })
+    }
+
+    /// Returns the age of the entry.
+    pub fn age(&self) -> Duration {
+        self.age_at(Utc::now().timestamp())
+    }
+
+    /// Returns the age of the entry at the given Unix timestamp.
+    pub fn age_at(&self, now: i64) -> Duration {
+        Duration::from_secs((now - self.modified).max(0) as u64)
+    }
+
+    /// Removes the entry along with its metadata.
+    pub fn remove(&self) -> Result<(), ReproStatusError> {
+        fs::remove_file(&self.path)?;
+        if self.log_type.is_some() {
+            let metadata_path = LogMetadata::get_path(&self.path);
+            if metadata_path.exists() {
+                fs::remove_file(metadata_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the paths of the files in a directory whose names match the given filter.
+fn get_files(dir: &Path, filter: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(&filter)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns `true` if the given file name belongs to a finished file with the given suffix.
+fn is_finished_file(name: &str, suffix: &str) -> bool {
+    !name.starts_with('.') && name.ends_with(suffix)
+}
+
+/// Returns `true` if the given file name belongs to a temporary file.
+fn is_temp_file(name: &str) -> bool {
+    name.starts_with('.') && name.ends_with(TEMP_SUFFIX)
+}
+
+/// Returns the entries of the cache directory from the oldest to the newest.
+///
+/// The temporary files of the writes that are interrupted (i.e. not modified
+/// for a while) are included as partial entries.
+pub fn get_entries(cache_dir: &Path) -> Result<Vec<CacheEntry>, ReproStatusError> {
+    let mut entries = Vec::new();
+    let mut dirs = vec![cache_dir.join(HTTP_DIR)];
+    if let Ok(instances) = fs::read_dir(cache_dir.join(LOGS_DIR)) {
+        for instance in instances.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            for path in get_files(&instance, |name| is_finished_file(name, LOG_SUFFIX)) {
+                let log_type = match path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(LOG_SUFFIX))
+                {
+                    Some(name) if name.ends_with("_build") => LogType::Build,
+                    Some(name) if name.ends_with("_diffoscope") => LogType::Diffoscope,
+                    _ => continue,
+                };
+                entries.push(CacheEntry::new(path, Some(log_type))?);
+            }
+            dirs.push(instance);
+        }
+    }
+    for path in get_files(&cache_dir.join(HTTP_DIR), |name| {
+        is_finished_file(name, ".json")
+    }) {
+        entries.push(CacheEntry::new(path, None)?);
+    }
+    for dir in dirs {
+        for path in get_files(&dir, is_temp_file) {
+            let entry = CacheEntry::new(path, None)?;
+            if entry.age() > STALE_TEMP_AGE {
+                entries.push(entry);
+            }
+        }
+    }
+    entries.sort_by_key(|entry| entry.modified);
+    Ok(entries)
+}
+
+/// Returns the entries to remove for satisfying the given limits.
+///
+/// The entries that are older than the maximum age are removed first and then
+/// the oldest entries are removed until the total size is within the maximum size.
+/// The entries are expected to be sorted from the oldest to the newest.
+pub fn get_prunable_entries(
+    entries: &[CacheEntry],
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+) -> Vec<&CacheEntry> {
+    let (mut prunable, remaining): (Vec<&CacheEntry>, Vec<&CacheEntry>) = entries
+        .iter()
+        .partition(|entry| max_age.is_some_and(|max_age| entry.age() > max_age));
+    if let Some(max_size) = max_size {
+        let mut total_size = remaining.iter().map(|entry| entry.size).sum::<u64>();
+        for entry in remaining {
+            if total_size <= max_size {
+                break;
+            }
+            total_size -= entry.size;
+            prunable.push(entry);
+        }
+    }
+    prunable
+}
+
+/// Removes the oldest cached logs until their total size is within the given size.
+///
+/// The log at the given path is never removed. Returns the removed entries.
+pub fn enforce_log_size(
+    cache_dir: &Path,
+    max_size: u64,
+    keep: &Path,
+) -> Result<Vec<CacheEntry>, ReproStatusError> {
+    let mut entries = get_entries(cache_dir)?;
+    entries.retain(|entry| entry.log_type.is_some() || entry.instance.is_some());
+    let kept_size = entries
+        .iter()
+        .filter(|entry| entry.path == keep)
+        .map(|entry| entry.size)
+        .sum::<u64>();
+    entries.retain(|entry| entry.path != keep);
+    let removed = get_prunable_entries(&entries, None, Some(max_size.saturating_sub(kept_size)))
+        .into_iter()
+        .cloned()
+        .collect::<Vec<CacheEntry>>();
+    for entry in &removed {
+        entry.remove()?;
+    }
+    Ok(removed)
+}
+
+/// Returns the human readable representation of the given duration.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// Cached HTTP response.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CachedResponse {
+    /// URL of the request.
+    pub url: String,
+    /// Unix timestamp of the time that the response is fetched.
+    pub fetched_at: i64,
+    /// Body of the response.
+    pub body: String,
+    /// Value of the `ETag` header.
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header.
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+impl CachedResponse {
+    /// Constructs a new instance that is fetched now.
+    pub fn new(
+        url: &str,
+        body: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Self {
+        Self {
+            url: url.to_string(),
+            fetched_at: Utc::now().timestamp(),
+            body,
+            etag,
+            last_modified,
+        }
+    }
+
+    /// Returns the age of the response.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs((Utc::now().timestamp() - self.fetched_at).max(0) as u64)
+    }
+}
+
+/// Cache for the responses of HTTP requests.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    /// Directory to store the responses in.
+    dir: PathBuf,
+    /// Maximum age of a response to use it without sending a request.
+    pub max_age: Duration,
+    /// Whether if the requests should not be sent at all.
+    pub offline: bool,
+    /// Whether if the cached responses should be used when the requests fail.
+    pub fallback: bool,
+    /// Fetch time of the oldest cached response that is used instead of a request.
+    oldest_used: Arc<Mutex<Option<i64>>>,
+}
+
+impl HttpCache {
+    /// Constructs a new instance.
+    pub fn new(cache_dir: PathBuf, max_age: Duration, offline: bool) -> Self {
+        Self {
+            dir: cache_dir.join(HTTP_DIR),
+            max_age,
+            offline,
+            fallback: false,
+            oldest_used: Arc::default(),
+        }
+    }
+
+    /// Returns the path of the cached response for the given URL.
+    fn get_path(&self, url: &str) -> PathBuf {
+        let name = sanitize_file_name(url.split_once("://").map_or(url, |(_, url)| url));
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// Returns the cached response for the given URL.
+    ///
+    /// Corrupted cache files and the responses of the other URLs that share
+    /// the same file name (e.g. `a-b` and `a_b`) are ignored.
+    pub fn read(&self, url: &str) -> Option<CachedResponse> {
+        let path = self.get_path(url);
+        let contents = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str::<CachedResponse>(&contents) {
+            Ok(response) if response.url == url => Some(response),
+            Ok(response) => {
+                log::debug!(
+                    "Ignoring the cached response of {} for {}",
+                    response.url,
+                    url
+                );
+                None
+            }
+            Err(e) => {
+                log::debug!("Ignoring invalid cache file {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Saves the response.
+    pub fn write(&self, response: &CachedResponse) -> Result<(), ReproStatusError> {
+        write_atomic(
+            &self.get_path(&response.url),
+            serde_json::to_string(response)?.as_bytes(),
+        )
+    }
+
+    /// Records that the cached response is used instead of sending a request.
+    pub fn mark_used(&self, response: &CachedResponse) {
+        if let Ok(mut oldest_used) = self.oldest_used.lock() {
+            *oldest_used = Some(oldest_used.map_or(response.fetched_at, |fetched_at| {
+                fetched_at.min(response.fetched_at)
+            }));
+        }
+    }
+
+    /// Returns the fetch time of the oldest cached response that is used.
+    pub fn oldest_used(&self) -> Option<i64> {
+        self.oldest_used
+            .lock()
+            .ok()
+            .and_then(|oldest_used| *oldest_used)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::mem;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_http_cache() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let cache_dir = temp_dir.path().to_path_buf();
+        let cache = HttpCache::new(cache_dir.clone(), Duration::from_secs(60), false);
+        let url = "https://archlinux.org/packages/search/json/?maintainer=orhun";
+        assert_eq!(None, cache.read(url));
+        let response = CachedResponse::new(
+            url,
+            String::from("{}"),
+            Some(String::from("\"etag\"")),
+            None,
+        );
+        cache.write(&response)?;
+        assert_eq!(Some(response.clone()), cache.read(url));
+        let colliding =
+            CachedResponse::new(&url.replace("orhun", "a-b"), String::new(), None, None);
+        let other_url = url.replace("orhun", "a_b");
+        assert_eq!(cache.get_path(&colliding.url), cache.get_path(&other_url));
+        cache.write(&colliding)?;
+        assert_eq!(None, cache.read(&other_url));
+        assert_eq!(Some(colliding.clone()), cache.read(&colliding.url));
+        assert_eq!(None, cache.oldest_used());
+        cache.mark_used(&CachedResponse {
+            fetched_at: response.fetched_at - 60,
+            ..response.clone()
+        });
+        cache.clone().mark_used(&response);
+        assert_eq!(Some(response.fetched_at - 60), cache.oldest_used());
+        assert!(cache_dir
+            .join("http")
+            .join("archlinux_org_packages_search_json__maintainer_orhun.json")
+            .exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_cache() -> Result<()> {
+        assert_eq!(
+            "reproducible.archlinux.org",
+            get_instance_dir_name("https://reproducible.archlinux.org/")
+        );
+        assert_eq!(
+            "rebuilder.example.com_8484",
+            get_instance_dir_name("http://rebuilder.example.com:8484")
+        );
+        assert_eq!(
+            "rebuilder.example.com_8484_arch_rebuilderd",
+            get_instance_dir_name("http://rebuilder.example.com:8484/arch/rebuilderd/")
+        );
+        assert_eq!("unknown", get_instance_dir_name(""));
+        let temp_dir = tempfile::tempdir()?;
+        let cache_dir = temp_dir.path().to_path_buf();
+        for name in ["1_build.log", "2_diffoscope.log", "x_build.log"] {
+            fs::write(cache_dir.join(name), name)?;
+        }
+        assert_eq!(2, remove_legacy_logs(&cache_dir)?);
+        assert!(!cache_dir.join("1_build.log").exists());
+        assert!(!cache_dir.join("2_diffoscope.log").exists());
+        assert!(cache_dir.join("x_build.log").exists());
+        let log_dir = cache_dir.join(LOGS_DIR).join("rebuilder.example.com");
+        let log_path = log_dir.join("1_build.log.zst");
+        let mut writer = LogWriter::new(&log_path)?;
+        writer.write_all(b"1_build.log")?;
+        writer.finish()?;
+        let mut log = String::new();
+        read_log(&log_path)?.read_to_string(&mut log)?;
+        assert_eq!("1_build.log", log);
+        let mut writer = LogWriter::new(&log_dir.join("3_build.log.zst"))?;
+        writer.write_all(b"partial")?;
+        drop(writer);
+        assert_eq!(1, fs::read_dir(&log_dir)?.count());
+        let metadata = LogMetadata {
+            pkgname: String::from("test"),
+            version: String::from("0.1-1"),
+            build_id: 1,
+            log_type: LogType::Build,
+            url: String::from("https://rebuilder.example.com/api/v0/builds/1/log"),
+            fetched_at: 0,
+        };
+        metadata.write(&log_path)?;
+        assert!(log_dir.join("1_build.json").exists());
+        assert_eq!(Some(metadata), LogMetadata::read(&log_path));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_entries() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let cache_dir = temp_dir.path().to_path_buf();
+        let log_dir = cache_dir.join(LOGS_DIR).join("reproducible.archlinux.org");
+        fs::create_dir_all(&log_dir)?;
+        for (name, size) in [("1_build.log.zst", 10), ("2_diffoscope.log.zst", 20)] {
+            let mut writer = LogWriter::new(&log_dir.join(name))?;
+            writer.write_all("x".repeat(size).as_bytes())?;
+            writer.finish()?;
+        }
+        LogMetadata {
+            pkgname: String::from("test"),
+            version: String::from("0.1-1"),
+            build_id: 2,
+            log_type: LogType::Diffoscope,
+            url: String::new(),
+            fetched_at: 0,
+        }
+        .write(&log_dir.join("2_diffoscope.log.zst"))?;
+        let cache = HttpCache::new(cache_dir.clone(), Duration::ZERO, false);
+        cache.write(&CachedResponse::new(
+            "https://x.org",
+            String::new(),
+            None,
+            None,
+        ))?;
+        let mut writer = LogWriter::new(&log_dir.join("3_build.log.zst"))?;
+        writer.write_all(b"partial")?;
+        mem::forget(writer);
+        let temp_path = get_files(&log_dir, is_temp_file)
+            .pop()
+            .expect("no temporary file");
+        assert_eq!(3, get_entries(&cache_dir)?.len());
+        File::options()
+            .write(true)
+            .open(&temp_path)?
+            .set_modified(SystemTime::now() - STALE_TEMP_AGE * 2)?;
+        let partial = get_entries(&cache_dir)?.remove(0);
+        assert!(partial.is_partial);
+        assert_eq!("3_build", partial.name);
+        assert_eq!(
+            Some("reproducible.archlinux.org"),
+            partial.instance.as_deref()
+        );
+        partial.remove()?;
+        assert!(!temp_path.exists());
+        let mut entries = get_entries(&cache_dir)?;
+        entries.sort_by_key(|entry| entry.path.clone());
+        assert_eq!(3, entries.len());
+        assert_eq!(None, entries[0].log_type);
+        assert_eq!("x_org", entries[0].name);
+        assert_eq!(Some(LogType::Build), entries[1].log_type);
+        assert_eq!("1_build", entries[1].name);
+        assert_eq!(fs::metadata(&entries[1].path)?.len(), entries[1].size);
+        assert_eq!(Some(LogType::Diffoscope), entries[2].log_type);
+        assert_eq!(
+            Some("reproducible.archlinux.org"),
+            entries[2].instance.as_deref()
+        );
+        assert_eq!("test 0.1-1", entries[2].name);
+        assert!(entries[2].size > fs::metadata(&entries[2].path)?.len());
+        let removed = enforce_log_size(&cache_dir, 15, &log_dir.join("2_diffoscope.log.zst"))?;
+        assert_eq!(1, removed.len());
+        assert!(!log_dir.join("1_build.log.zst").exists());
+        assert!(log_dir.join("2_diffoscope.log.zst").exists());
+        entries[2].remove()?;
+        assert!(!log_dir.join("2_diffoscope.json").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prunable_entries() {
+        let now = Utc::now().timestamp();
+        let entries = [(300, 10), (200, 20), (100, 30), (0, 40)]
+            .into_iter()
+            .map(|(age, size)| CacheEntry {
+                path: PathBuf::from(format!("{}", age)),
+                log_type: Some(LogType::Build),
+                is_partial: false,
+                instance: None,
+                name: String::new(),
+                size,
+                modified: now - age,
+            })
+            .collect::<Vec<CacheEntry>>();
+        let get_sizes = |entries: Vec<&CacheEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.size)
+                .collect::<Vec<u64>>()
+        };
+        assert_eq!(
+            vec![10, 20],
+            get_sizes(get_prunable_entries(
+                &entries,
+                Some(Duration::from_secs(150)),
+                None
+            ))
+        );
+        assert_eq!(
+            vec![10, 20],
+            get_sizes(get_prunable_entries(&entries, None, Some(70)))
+        );
+        assert_eq!(
+            vec![10, 20, 30],
+            get_sizes(get_prunable_entries(
+                &entries,
+                Some(Duration::from_secs(250)),
+                Some(40)
+            ))
+        );
+        assert!(get_prunable_entries(&entries, None, None).is_empty());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!("5s", format_duration(Duration::from_secs(5)));
+        assert_eq!("2m 5s", format_duration(Duration::from_secs(125)));
+        assert_eq!("1h 1m", format_duration(Duration::from_secs(3665)));
+        assert_eq!("2d 3h", format_duration(Duration::from_secs(183600)));
+    }
+}
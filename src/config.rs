@@ -0,0 +1,233 @@
+//! Configuration file.
+
+use crate::error::ReproStatusError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Returns the default path of the configuration file.
+///
+/// It is `$XDG_CONFIG_HOME/arch-repro-status/config.toml`
+pub fn get_default_config_path() -> Option<PathBuf> {
+    dirs_next::config_dir().map(|p| p.join(env!("CARGO_PKG_NAME")).join(CONFIG_FILE))
+}
+
+/// Settings that can be specified in the configuration file.
+///
+/// Every setting corresponds to a command-line argument.
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Address of the rebuilderd instance(s).
+    pub rebuilderd: Option<Vec<String>>,
+    /// Policy for combining the results of multiple rebuilderd instances.
+    pub policy: Option<String>,
+    /// Path of the pacman configuration file.
+    pub pacman_conf: Option<PathBuf>,
+    /// Path to the pacman database.
+    pub dbpath: Option<String>,
+    /// Repositories to query.
+    pub repos: Option<Vec<String>>,
+    /// Filter for package status.
+    pub filter: Option<String>,
+    /// Number of the rebuilderd instances that need to report GOOD.
+    pub require: Option<usize>,
+    /// Name of the trusted set of rebuilderd instances.
+    pub trusted_set: Option<String>,
+    /// Package statuses that result in a failure exit code.
+    pub fail_on: Option<Vec<String>>,
+    /// Output format.
+    pub output: Option<String>,
+    /// Columns to include in the CSV/TSV output.
+    pub columns: Option<Vec<String>>,
+    /// Field to group the results by.
+    pub group_by: Option<String>,
+    /// Hex encoded public keys of the trusted rebuilders.
+    pub trusted_keys: Option<Vec<String>>,
+    /// Pager for viewing files.
+    pub pager: Option<String>,
+    /// Cache directory.
+    pub cache_dir: Option<PathBuf>,
+    /// Maximum age (in seconds) of the cached data to use without fetching.
+    pub max_age: Option<u64>,
+    /// Maximum total size of the cached logs.
+    pub cache_size: Option<String>,
+}
+
+impl Settings {
+    /// Merges the settings with the given settings.
+    ///
+    /// The given settings take precedence.
+    pub fn merge(self, other: Settings) -> Self {
+        Self {
+            rebuilderd: other.rebuilderd.or(self.rebuilderd),
+            policy: other.policy.or(self.policy),
+            pacman_conf: other.pacman_conf.or(self.pacman_conf),
+            dbpath: other.dbpath.or(self.dbpath),
+            repos: other.repos.or(self.repos),
+            filter: other.filter.or(self.filter),
+            require: other.require.or(self.require),
+            trusted_set: other.trusted_set.or(self.trusted_set),
+            fail_on: other.fail_on.or(self.fail_on),
+            output: other.output.or(self.output),
+            columns: other.columns.or(self.columns),
+            group_by: other.group_by.or(self.group_by),
+            trusted_keys: other.trusted_keys.or(self.trusted_keys),
+            pager: other.pager.or(self.pager),
+            cache_dir: other.cache_dir.or(self.cache_dir),
+            max_age: other.max_age.or(self.max_age),
+            cache_size: other.cache_size.or(self.cache_size),
+        }
+    }
+
+    /// Returns the settings as default values of the command-line arguments.
+    ///
+    /// Each item consists of the argument ID and the value.
+    pub fn get_defaults(&self) -> Vec<(&'static str, String)> {
+        [
+            ("rebuilderd", self.rebuilderd.as_ref().map(|v| v.join(","))),
+            ("policy", self.policy.clone()),
+            (
+                "pacman-conf",
+                self.pacman_conf
+                    .as_ref()
+                    .map(|v| v.to_string_lossy().to_string()),
+            ),
+            ("dbpath", self.dbpath.clone()),
+            ("repos", self.repos.as_ref().map(|v| v.join(","))),
+            ("filter", self.filter.clone()),
+            ("require", self.require.map(|v| v.to_string())),
+            ("trusted-set", self.trusted_set.clone()),
+            ("fail-on", self.fail_on.as_ref().map(|v| v.join(","))),
+            ("output", self.output.clone()),
+            ("columns", self.columns.as_ref().map(|v| v.join(","))),
+            ("group-by", self.group_by.clone()),
+            (
+                "trusted-keys",
+                self.trusted_keys.as_ref().map(|v| v.join(",")),
+            ),
+            ("pager", self.pager.clone()),
+            (
+                "cache-dir",
+                self.cache_dir
+                    .as_ref()
+                    .map(|v| v.to_string_lossy().to_string()),
+            ),
+            ("max-age", self.max_age.map(|v| v.to_string())),
+            ("cache-size", self.cache_size.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(id, value)| value.map(|value| (id, value)))
+        .collect()
+    }
+}
+
+/// Configuration file that consists of settings, named profiles
+/// and trusted sets of rebuilderd instances.
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+pub struct Config {
+    /// Default settings.
+    #[serde(flatten)]
+    pub settings: Settings,
+    /// Named profiles that override the default settings.
+    #[serde(default)]
+    pub profile: HashMap<String, Settings>,
+    /// Named sets of trusted rebuilderd instances.
+    #[serde(default, rename = "trusted-sets")]
+    pub trusted_sets: HashMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// Parses the configuration file at the given path.
+    pub fn load(path: &Path) -> Result<Self, ReproStatusError> {
+        log::debug!("loading configuration file: {:?}", path);
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Returns the settings of the given profile merged with the default settings.
+    pub fn get_settings(self, profile: Option<&str>) -> Result<Settings, ReproStatusError> {
+        let mut profiles = self.profile;
+        match profile {
+            Some(name) => match profiles.remove(name) {
+                Some(profile) => Ok(self.settings.merge(profile)),
+                None => Err(ReproStatusError::ConfigError(format!(
+                    "profile not found: `{}`",
+                    name
+                ))),
+            },
+            None => Ok(self.settings),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{Args, OutputFormat};
+    use anyhow::Result;
+    use clap::FromArgMatches;
+    use pretty_assertions::assert_eq;
+
+    /// Configuration to use for testing.
+    const CONFIG: &str = r#"
+        rebuilderd = ["https://reproducible.archlinux.org"]
+        pager = "bat"
+        output = "json"
+
+        [trusted-sets]
+        independent = ["https://rebuilder.example.com"]
+
+        [profile.work]
+        rebuilderd = ["https://rebuilder.example.com", "https://reproducible.archlinux.org"]
+        max-age = 3600
+    "#;
+
+    #[test]
+    fn test_config() -> Result<()> {
+        let config = toml::from_str::<Config>(CONFIG)?;
+        assert_eq!(Some(String::from("bat")), config.settings.pager);
+        assert_eq!(
+            Some(&vec![String::from("https://rebuilder.example.com")]),
+            config.trusted_sets.get("independent")
+        );
+        assert!(config.clone().get_settings(Some("home")).is_err());
+        let settings = config.get_settings(Some("work"))?;
+        assert_eq!(
+            vec![
+                (
+                    "rebuilderd",
+                    String::from(
+                        "https://rebuilder.example.com,https://reproducible.archlinux.org"
+                    )
+                ),
+                ("output", String::from("json")),
+                ("pager", String::from("bat")),
+                ("max-age", String::from("3600")),
+            ],
+            settings.get_defaults()
+        );
+        let args = Args::from_arg_matches(
+            &Args::command_with_settings(&settings)?.try_get_matches_from([
+                env!("CARGO_PKG_NAME"),
+                "--pager",
+                "less",
+            ])?,
+        )?;
+        assert_eq!(2, args.rebuilderd.len());
+        assert_eq!(OutputFormat::Json, args.output);
+        assert_eq!("less", args.pager);
+        assert_eq!(3600, args.max_age);
+        assert!(Args::command_with_settings(&settings)?
+            .try_get_matches_from([env!("CARGO_PKG_NAME"), "--hook"])
+            .is_ok());
+        assert!(Args::command_with_settings(&Settings {
+            columns: Some(vec![String::from("pkgname"), String::from("xyz")]),
+            ..Settings::default()
+        })
+        .is_err());
+        Ok(())
+    }
+}
@@ -14,6 +14,34 @@ pub enum ReproStatusError {
     /// Error that may occur when processing a request.
     #[error("failed to send request: `{0}`")]
     RequestError(#[from] reqwest::Error),
+    /// Error that may occur when the server responds with an unsuccessful status.
+    #[error("HTTP error: `{url}` responded with {status} ({body:?})")]
+    HttpError {
+        /// Status code of the response.
+        status: reqwest::StatusCode,
+        /// Requested URL.
+        url: String,
+        /// Excerpt of the response body.
+        body: String,
+    },
+    /// Error that may occur while (de)serializing JSON.
+    #[error("JSON error: `{0}`")]
+    JsonError(#[from] serde_json::Error),
+    /// Error that may occur while writing CSV/TSV.
+    #[error("CSV error: `{0}`")]
+    CsvError(#[from] csv::Error),
+    /// Error that may occur when the cached data is not available in offline mode.
+    #[error("no cached data is available for `{0}` in offline mode")]
+    CacheMissError(String),
+    /// Error that may occur while parsing TOML.
+    #[error("TOML error: `{0}`")]
+    TomlError(#[from] toml::de::Error),
+    /// Error that may occur while loading the configuration.
+    #[error("configuration error: {0}")]
+    ConfigError(String),
+    /// Error that may occur while verifying attestations.
+    #[error("attestation error: {0}")]
+    AttestationError(String),
     /// Error that may occur while handling Ctrl-C signals.
     #[error("Ctrl-C error: `{0}`")]
     SignalError(#[from] ctrlc::Error),
@@ -21,3 +49,41 @@ pub enum ReproStatusError {
     #[error("unknown error")]
     Unknown,
 }
+
+/// Exit codes of the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Run finished successfully.
+    Success = 0,
+    /// Generic failure.
+    Failure = 1,
+    /// Some packages are not reproducible.
+    BadPackages = 3,
+    /// Some packages have unknown reproducibility status.
+    UnknownPackages = 4,
+    /// Network error.
+    NetworkError = 5,
+    /// ALPM error.
+    AlpmError = 6,
+    /// I/O error.
+    IoError = 7,
+    /// Some packages are not rebuilt for the checked version.
+    StalePackages = 8,
+    /// Some package files differ from the rebuilt artifacts.
+    MismatchedPackages = 9,
+    /// Some packages do not satisfy the threshold policy.
+    PolicyFailure = 10,
+}
+
+impl<'a> From<&'a ReproStatusError> for ExitCode {
+    fn from(error: &'a ReproStatusError) -> Self {
+        match error {
+            ReproStatusError::IoError(_) => Self::IoError,
+            ReproStatusError::AlpmError(_) => Self::AlpmError,
+            ReproStatusError::RequestError(_) | ReproStatusError::HttpError { .. } => {
+                Self::NetworkError
+            }
+            _ => Self::Failure,
+        }
+    }
+}
@@ -1,32 +1,151 @@
 use crate::archweb::{ArchwebPackage, SearchResult, ARCHWEB_ENDPOINT};
+use crate::attestation::Attestation;
+use crate::cache::{format_duration, CachedResponse, HttpCache};
 use crate::error::ReproStatusError;
 use crate::package::LogType;
 use rebuilderd_common::PkgRelease as RebuilderdPackage;
-use reqwest::Client as HttpClient;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client as HttpClient, Response, StatusCode};
+use sha2::{Digest, Sha256};
+
+/// Maximum number of characters of the response body to include in the errors.
+const ERROR_BODY_LENGTH: usize = 200;
+
+/// Returns the error for an unsuccessful response with an excerpt of its body.
+fn get_http_error(status: StatusCode, url: &str, body: &str) -> ReproStatusError {
+    let body = body.trim();
+    ReproStatusError::HttpError {
+        status,
+        url: url.to_string(),
+        body: match body.char_indices().nth(ERROR_BODY_LENGTH) {
+            Some((i, _)) => format!("{}...", &body[..i]),
+            None => body.to_string(),
+        },
+    }
+}
+
+/// Returns the response if it has a successful status.
+async fn check_status(response: Response) -> Result<Response, ReproStatusError> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        let url = response.url().to_string();
+        let body = response.text().await.unwrap_or_default();
+        Err(get_http_error(status, &url, &body))
+    }
+}
+
+/// Returns the value of the given header as string.
+fn get_header(headers: &HeaderMap, name: impl reqwest::header::AsHeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+/// Fetches the given URL or returns the cached response.
+///
+/// The cached response is used if it is not older than the maximum age
+/// or the cache is in offline mode. Otherwise, it is revalidated via
+/// a conditional request and reused if the server responds with 304.
+/// If the request fails and the cache has a fallback enabled, the cached
+/// response is used regardless of its age.
+///
+/// The cache file is rewritten as a whole on 304 to refresh its fetch time,
+/// since the metadata and the body are stored together.
+async fn fetch_cached<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    url: &'a str,
+) -> Result<String, ReproStatusError> {
+    let cached = cache.read(url);
+    if let Some(response) = &cached {
+        let age = response.age();
+        if age <= cache.max_age || cache.offline {
+            let message = format!(
+                "Using cached data for {} ({} old)",
+                url,
+                format_duration(age)
+            );
+            if age > cache.max_age {
+                log::warn!("{}", message);
+            } else {
+                log::info!("{}", message);
+            }
+            cache.mark_used(response);
+            return Ok(response.body.to_string());
+        }
+    }
+    if cache.offline {
+        return Err(ReproStatusError::CacheMissError(url.to_string()));
+    }
+    log::debug!("Fetching {}", url);
+    let mut request = client.get(url);
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let result = async {
+        let response = request.send().await?;
+        let headers = response.headers().clone();
+        Ok::<CachedResponse, ReproStatusError>(match &cached {
+            Some(cached) if response.status() == StatusCode::NOT_MODIFIED => {
+                log::debug!("{} is not modified, reusing the cached data", url);
+                CachedResponse::new(
+                    url,
+                    cached.body.to_string(),
+                    get_header(&headers, ETAG).or_else(|| cached.etag.clone()),
+                    get_header(&headers, LAST_MODIFIED).or_else(|| cached.last_modified.clone()),
+                )
+            }
+            _ => CachedResponse::new(
+                url,
+                check_status(response).await?.text().await?,
+                get_header(&headers, ETAG),
+                get_header(&headers, LAST_MODIFIED),
+            ),
+        })
+    }
+    .await;
+    let response = match (result, cached) {
+        (Ok(response), _) => response,
+        (Err(e), Some(cached)) if cache.fallback => {
+            log::warn!(
+                "Using cached data for {} ({} old) since the request failed: {}",
+                url,
+                format_duration(cached.age()),
+                e
+            );
+            cache.mark_used(&cached);
+            return Ok(cached.body);
+        }
+        (Err(e), _) => return Err(e),
+    };
+    cache.write(&response)?;
+    Ok(response.body)
+}
 
 /// Fetches the packages of the specified maintainer from archlinux.org
 pub async fn fetch_archweb_packages<'a>(
     client: &'a HttpClient,
+    cache: &'a HttpCache,
     maintainer: &'a str,
 ) -> Result<Vec<ArchwebPackage>, ReproStatusError> {
     let url = format!("{}/?maintainer={}", ARCHWEB_ENDPOINT, maintainer);
-    let response = client
-        .get(&url)
-        .send()
-        .await?
-        .json::<SearchResult>()
-        .await?;
+    let response = serde_json::from_str::<SearchResult>(&fetch_cached(client, cache, &url).await?)?;
     let mut results = response.results;
     if let (Some(page), Some(num_pages)) = (response.page, response.num_pages) {
         for page in (page + 1)..=num_pages {
             results.extend(
-                client
-                    .get(&format!("{}&page={}", &url, page))
-                    .send()
-                    .await?
-                    .json::<SearchResult>()
-                    .await?
-                    .results,
+                serde_json::from_str::<SearchResult>(
+                    &fetch_cached(client, cache, &format!("{}&page={}", &url, page)).await?,
+                )?
+                .results,
             )
         }
     }
@@ -36,37 +155,77 @@ pub async fn fetch_archweb_packages<'a>(
 /// Fetches the packages from the specified rebuilderd instance.
 pub async fn fetch_rebuilderd_packages<'a>(
     client: &'a HttpClient,
+    cache: &'a HttpCache,
     rebuilder: &'a str,
 ) -> Result<Vec<RebuilderdPackage>, ReproStatusError> {
-    Ok(client
-        .get(format!("{}/api/v0/pkgs/list?distro=archlinux", rebuilder))
-        .send()
-        .await?
-        .json()
-        .await?)
+    Ok(serde_json::from_str(
+        &fetch_cached(
+            client,
+            cache,
+            &format!("{}/api/v0/pkgs/list?distro=archlinux", rebuilder),
+        )
+        .await?,
+    )?)
+}
+
+/// Fetches the in-toto attestation of a build from the specified rebuilderd instance.
+pub async fn fetch_rebuilderd_attestation<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    rebuilder: &'a str,
+    build_id: i32,
+) -> Result<Attestation, ReproStatusError> {
+    Ok(serde_json::from_str(
+        &fetch_cached(
+            client,
+            cache,
+            &format!("{}/api/v0/builds/{}/attestation", rebuilder, build_id),
+        )
+        .await?,
+    )?)
+}
+
+/// Returns the URL of the package logs on the specified rebuilderd instance.
+pub fn get_rebuilderd_log_url(rebuilder: &str, build_id: i32, log_type: LogType) -> String {
+    format!(
+        "{}/api/v0/builds/{}/{}",
+        rebuilder,
+        build_id,
+        match log_type {
+            LogType::Build => "log",
+            LogType::Diffoscope => "diffoscope",
+        }
+    )
 }
 
-/// Fetches the package logs from the specified rebuilderd instance.
+/// Requests the package logs from the specified rebuilderd instance.
+///
+/// The response is returned without reading its body so that it can be streamed.
 pub async fn fetch_rebuilderd_logs<'a>(
     client: &'a HttpClient,
     rebuilder: &'a str,
     build_id: i32,
     log_type: LogType,
-) -> Result<String, ReproStatusError> {
-    Ok(client
-        .get(&format!(
-            "{}/api/v0/builds/{}/{}",
-            rebuilder,
-            build_id,
-            match log_type {
-                LogType::Build => "log",
-                LogType::Diffoscope => "diffoscope",
-            }
-        ))
+) -> Result<Response, ReproStatusError> {
+    let response = client
+        .get(get_rebuilderd_log_url(rebuilder, build_id, log_type))
         .send()
-        .await?
-        .text()
-        .await?)
+        .await?;
+    check_status(response).await
+}
+
+/// Fetches the artifact at the given URL and returns its SHA-256 checksum.
+pub async fn fetch_artifact_checksum<'a>(
+    client: &'a HttpClient,
+    url: &'a str,
+) -> Result<String, ReproStatusError> {
+    log::debug!("fetching artifact: {}", url);
+    let mut response = check_status(client.get(url).send().await?).await?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 #[cfg(test)]
@@ -74,34 +233,99 @@ mod tests {
     use super::*;
     use anyhow::Result;
     use pretty_assertions::assert_eq;
+    use std::path::Path;
+    use std::time::Duration;
 
     /// Rebuilderd instance to use for testing.
     const REBUILDERD_URL: &str = "https://reproducible.archlinux.org";
 
+    /// Returns the cache to use for testing.
+    fn get_test_cache(cache_dir: &Path, offline: bool) -> HttpCache {
+        HttpCache::new(cache_dir.to_path_buf(), Duration::ZERO, offline)
+    }
+
     #[tokio::test]
     async fn test_fetch_archweb_packages() -> Result<()> {
         let client = HttpClient::new();
-        assert_eq!(0, fetch_archweb_packages(&client, "xyz").await?.len());
-        assert!(!fetch_archweb_packages(&client, "jelle").await?.is_empty());
+        let cache_dir = tempfile::tempdir()?;
+        let cache = get_test_cache(cache_dir.path(), false);
+        assert_eq!(
+            0,
+            fetch_archweb_packages(&client, &cache, "xyz").await?.len()
+        );
+        assert!(!fetch_archweb_packages(&client, &cache, "jelle")
+            .await?
+            .is_empty());
         Ok(())
     }
 
     #[tokio::test]
     async fn test_fetch_rebuilderd_packages() -> Result<()> {
         let client = HttpClient::new();
-        assert!(!fetch_rebuilderd_packages(&client, REBUILDERD_URL)
+        let cache_dir = tempfile::tempdir()?;
+        let cache = get_test_cache(cache_dir.path(), false);
+        assert!(!fetch_rebuilderd_packages(&client, &cache, REBUILDERD_URL)
             .await?
             .is_empty());
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_fetch_offline() -> Result<()> {
+        let client = HttpClient::new();
+        let cache_dir = tempfile::tempdir()?;
+        let cache = get_test_cache(cache_dir.path(), true);
+        assert!(matches!(
+            fetch_rebuilderd_packages(&client, &cache, "https://rebuilderd.invalid").await,
+            Err(ReproStatusError::CacheMissError(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_fallback() -> Result<()> {
+        let client = HttpClient::new();
+        let cache_dir = tempfile::tempdir()?;
+        let mut cache = get_test_cache(cache_dir.path(), false);
+        let url = "https://rebuilderd.invalid/api/v0/pkgs/list?distro=archlinux";
+        cache.write(&CachedResponse {
+            fetched_at: 0,
+            ..CachedResponse::new(url, String::from("[]"), None, None)
+        })?;
+        assert!(fetch_cached(&client, &cache, url).await.is_err());
+        assert_eq!(None, cache.oldest_used());
+        cache.fallback = true;
+        assert_eq!("[]", fetch_cached(&client, &cache, url).await?);
+        assert_eq!(Some(0), cache.oldest_used());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_fetch_rebuilderd_logs() -> Result<()> {
         let client = HttpClient::new();
+        assert!(matches!(
+            fetch_rebuilderd_logs(&client, REBUILDERD_URL, 0, LogType::Build).await,
+            Err(ReproStatusError::HttpError {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_http_error() {
+        let error = get_http_error(StatusCode::NOT_FOUND, "https://x.org", "Not found\n");
         assert_eq!(
-            "Not found\n",
-            fetch_rebuilderd_logs(&client, REBUILDERD_URL, 0, LogType::Build).await?
+            "HTTP error: `https://x.org` responded with 404 Not Found (\"Not found\")",
+            error.to_string()
         );
-        Ok(())
+        let body = "é".repeat(ERROR_BODY_LENGTH + 1);
+        match get_http_error(StatusCode::BAD_GATEWAY, "https://x.org", &body) {
+            ReproStatusError::HttpError { body, .. } => {
+                assert_eq!(ERROR_BODY_LENGTH + 3, body.chars().count())
+            }
+            _ => unreachable!(),
+        }
     }
 }
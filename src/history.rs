@@ -0,0 +1,417 @@
+//! History of the reproducibility results.
+
+use crate::cache;
+use crate::error::ReproStatusError;
+use crate::package::{Package, Status};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Error as IoError, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the history file.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Default maximum number of records to keep for each target.
+pub const DEFAULT_MAX_RECORDS: usize = 100;
+
+/// Returns the path of the history file.
+///
+/// It is `$XDG_DATA_HOME/arch-repro-status/history.jsonl`
+pub fn get_history_path() -> Result<PathBuf, ReproStatusError> {
+    Ok(dirs_next::data_dir()
+        .map(|p| p.join(env!("CARGO_PKG_NAME")).join(HISTORY_FILE))
+        .ok_or_else(|| IoError::other("cannot find data directory"))?)
+}
+
+/// Parses the given date (`YYYY-MM-DD` or RFC 3339) as a Unix timestamp.
+pub fn parse_date(date: &str) -> Result<i64, String> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.timestamp())
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(|date| date.and_hms(0, 0, 0).timestamp())
+        })
+        .map_err(|e| format!("invalid date: {:?} ({})", date, e))
+}
+
+/// Returns the human readable representation of the given Unix timestamp.
+pub fn format_timestamp(timestamp: i64) -> String {
+    DateTime::<Utc>::from_utc(chrono::NaiveDateTime::from_timestamp(timestamp, 0), Utc)
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string()
+}
+
+/// Recorded result of a package.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// Name of the package.
+    pub name: String,
+    /// Full version of the package.
+    pub version: String,
+    /// Reproducibility status.
+    pub status: Status,
+    /// Rebuilderd build ID.
+    pub build_id: i32,
+    /// Repository of the package.
+    #[serde(default)]
+    pub repo: String,
+    /// Maintainers of the package.
+    #[serde(default)]
+    pub maintainers: Vec<String>,
+}
+
+impl<'a> From<&'a Package> for HistoryEntry {
+    fn from(pkg: &'a Package) -> Self {
+        Self {
+            name: pkg.data.pkgname.to_string(),
+            version: pkg.data.version(),
+            status: pkg.status,
+            build_id: pkg.build_id,
+            repo: pkg.data.repo.to_string(),
+            maintainers: pkg.data.maintainers.clone(),
+        }
+    }
+}
+
+/// Recorded results of a run.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryRecord {
+    /// Unix timestamp of the run.
+    pub timestamp: i64,
+    /// Checked set of packages (e.g. `system` or `maintainer:orhun`).
+    pub target: String,
+    /// Results of the packages.
+    pub packages: Vec<HistoryEntry>,
+}
+
+impl HistoryRecord {
+    /// Constructs a new instance from the results of the current run.
+    pub fn new(target: &str, packages: &[Package]) -> Self {
+        Self {
+            timestamp: Utc::now().timestamp(),
+            target: target.to_string(),
+            packages: packages.iter().map(HistoryEntry::from).collect(),
+        }
+    }
+}
+
+/// History file that consists of a record per line.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// Path of the file.
+    path: PathBuf,
+}
+
+impl History {
+    /// Constructs a new instance.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Returns the path of the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the given record to the history.
+    ///
+    /// The oldest records of the same target are removed so that at most
+    /// the given number of records are kept for it.
+    pub fn append(
+        &self,
+        record: &HistoryRecord,
+        max_records: usize,
+    ) -> Result<(), ReproStatusError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        if self.load(&record.target)?.len() > max_records {
+            self.prune(Some(max_records), None)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the records that exceed the maximum number of records of their
+    /// target or that are older than the given Unix timestamp.
+    ///
+    /// The latest records of each target are kept. Returns the number of the
+    /// removed records.
+    pub fn prune(
+        &self,
+        max_records: Option<usize>,
+        since: Option<i64>,
+    ) -> Result<usize, ReproStatusError> {
+        let records = self.load_all()?;
+        let mut counts = HashMap::<&str, usize>::new();
+        let mut kept = Vec::new();
+        for record in records.iter().rev() {
+            let count = counts.entry(&record.target).or_default();
+            if max_records.is_some_and(|max_records| *count >= max_records)
+                || since.is_some_and(|since| record.timestamp < since)
+            {
+                continue;
+            }
+            *count += 1;
+            kept.push(record);
+        }
+        let removed = records.len() - kept.len();
+        if removed != 0 {
+            let mut contents = String::new();
+            for record in kept.into_iter().rev() {
+                contents.push_str(&serde_json::to_string(record)?);
+                contents.push('\n');
+            }
+            cache::write_atomic(&self.path, contents.as_bytes())?;
+        }
+        Ok(removed)
+    }
+
+    /// Returns all of the records in chronological order.
+    ///
+    /// Invalid lines are ignored.
+    pub fn load_all(&self) -> Result<Vec<HistoryRecord>, ReproStatusError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| match serde_json::from_str::<HistoryRecord>(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::debug!("Ignoring invalid history record: {}", e);
+                    None
+                }
+            })
+            .collect::<Vec<HistoryRecord>>();
+        records.sort_by_key(|record| record.timestamp);
+        Ok(records)
+    }
+
+    /// Returns the records of the given target in chronological order.
+    pub fn load(&self, target: &str) -> Result<Vec<HistoryRecord>, ReproStatusError> {
+        Ok(self
+            .load_all()?
+            .into_iter()
+            .filter(|record| record.target == target)
+            .collect())
+    }
+}
+
+/// Change of a package between two runs.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Change {
+    /// Name of the package.
+    pub name: String,
+    /// Previous version of the package.
+    pub old_version: Option<String>,
+    /// Current version of the package.
+    pub new_version: String,
+    /// Previous status of the package.
+    pub old_status: Option<Status>,
+    /// Current status of the package.
+    pub new_status: Status,
+}
+
+/// Changes between two runs.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct HistoryDiff {
+    /// Unix timestamp of the previous run.
+    pub from: i64,
+    /// Unix timestamp of the current run.
+    pub to: i64,
+    /// Packages that became BAD after being GOOD.
+    pub regressions: Vec<Change>,
+    /// Packages that became GOOD after being BAD.
+    pub fixes: Vec<Change>,
+    /// Packages that became UNKWN (or are new and UNKWN).
+    pub unknown: Vec<Change>,
+}
+
+impl HistoryDiff {
+    /// Compares the given runs.
+    pub fn new(old: &HistoryRecord, new: &HistoryRecord) -> Self {
+        let old_packages = old
+            .packages
+            .iter()
+            .map(|pkg| (pkg.name.as_str(), pkg))
+            .collect::<HashMap<&str, &HistoryEntry>>();
+        let mut diff = Self {
+            from: old.timestamp,
+            to: new.timestamp,
+            regressions: Vec::new(),
+            fixes: Vec::new(),
+            unknown: Vec::new(),
+        };
+        for pkg in &new.packages {
+            let old_pkg = old_packages.get(pkg.name.as_str());
+            let change = Change {
+                name: pkg.name.to_string(),
+                old_version: old_pkg.map(|old_pkg| old_pkg.version.to_string()),
+                new_version: pkg.version.to_string(),
+                old_status: old_pkg.map(|old_pkg| old_pkg.status),
+                new_status: pkg.status,
+            };
+            match (change.old_status, change.new_status) {
+                (Some(Status::Good), Status::Bad) => diff.regressions.push(change),
+                (Some(Status::Bad), Status::Good) => diff.fixes.push(change),
+                (Some(Status::Unknown), Status::Unknown) => {}
+                (_, Status::Unknown) => diff.unknown.push(change),
+                _ => {}
+            }
+        }
+        diff
+    }
+
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.regressions.is_empty() && self.fixes.is_empty() && self.unknown.is_empty()
+    }
+}
+
+/// Returns the records to compare for the given date.
+///
+/// The latest record is compared with the latest record before the date
+/// or with the previous record if the date is not given.
+pub fn get_records_to_compare(
+    records: &[HistoryRecord],
+    since: Option<i64>,
+) -> Option<(&HistoryRecord, &HistoryRecord)> {
+    let (new, previous) = records.split_last()?;
+    let old = match since {
+        Some(since) => previous
+            .iter()
+            .rev()
+            .find(|record| record.timestamp <= since)
+            .or_else(|| previous.first()),
+        None => previous.last(),
+    }?;
+    Some((old, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    /// Returns a record with the given packages.
+    fn get_test_record(timestamp: i64, packages: &[(&str, &str, Status)]) -> HistoryRecord {
+        HistoryRecord {
+            timestamp,
+            target: String::from("system"),
+            packages: packages
+                .iter()
+                .map(|(name, version, status)| HistoryEntry {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    status: *status,
+                    build_id: 0,
+                    repo: String::new(),
+                    maintainers: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_history() -> Result<()> {
+        let data_dir = tempfile::tempdir()?;
+        let path = data_dir.path().join("history").join(HISTORY_FILE);
+        let history = History::new(path.clone());
+        assert!(history.load("system")?.is_empty());
+        let first = get_test_record(20, &[("test", "0.1-1", Status::Good)]);
+        let second = get_test_record(10, &[("test", "0.1-1", Status::Bad)]);
+        history.append(&first, DEFAULT_MAX_RECORDS)?;
+        history.append(
+            &HistoryRecord {
+                target: String::from("maintainer:orhun"),
+                ..first.clone()
+            },
+            DEFAULT_MAX_RECORDS,
+        )?;
+        history.append(&second, DEFAULT_MAX_RECORDS)?;
+        assert_eq!(vec![second, first.clone()], history.load("system")?);
+        assert_eq!(3, history.load_all()?.len());
+        let third = get_test_record(30, &[("test", "0.1-2", Status::Good)]);
+        history.append(&third, 2)?;
+        assert_eq!(vec![first, third.clone()], history.load("system")?);
+        assert_eq!(1, history.load("maintainer:orhun")?.len());
+        assert_eq!(0, history.prune(Some(2), None)?);
+        assert_eq!(2, history.prune(None, Some(25))?);
+        assert_eq!(vec![third], history.load_all()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_diff() -> Result<()> {
+        let records = [
+            get_test_record(
+                100,
+                &[
+                    ("a", "1-1", Status::Good),
+                    ("b", "1-1", Status::Bad),
+                    ("c", "1-1", Status::Good),
+                ],
+            ),
+            get_test_record(
+                200,
+                &[
+                    ("a", "1-1", Status::Good),
+                    ("b", "1-1", Status::Bad),
+                    ("c", "1-1", Status::Good),
+                ],
+            ),
+            get_test_record(
+                300,
+                &[
+                    ("a", "1-2", Status::Bad),
+                    ("b", "1-2", Status::Good),
+                    ("c", "1-2", Status::Unknown),
+                    ("d", "1-1", Status::Unknown),
+                ],
+            ),
+        ];
+        let (old, new) = get_records_to_compare(&records, None).expect("no records");
+        assert_eq!((200, 300), (old.timestamp, new.timestamp));
+        let (old, _) = get_records_to_compare(&records, Some(150)).expect("no records");
+        assert_eq!(100, old.timestamp);
+        let (old, _) = get_records_to_compare(&records, Some(50)).expect("no records");
+        assert_eq!(100, old.timestamp);
+        assert!(get_records_to_compare(&records[..1], None).is_none());
+        let diff = HistoryDiff::new(&records[1], &records[2]);
+        assert_eq!(
+            vec!["a"],
+            diff.regressions
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(Some(String::from("1-1")), diff.regressions[0].old_version);
+        assert_eq!(
+            vec!["b"],
+            diff.fixes
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            vec!["c", "d"],
+            diff.unknown
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert!(HistoryDiff::new(&records[0], &records[1]).is_empty());
+        assert_eq!(Ok(86400), parse_date("1970-01-02"));
+        assert_eq!(Ok(3600), parse_date("1970-01-01T01:00:00Z"));
+        assert!(parse_date("yesterday").is_err());
+        assert_eq!("1970-01-02 00:00:00 UTC", format_timestamp(86400));
+        Ok(())
+    }
+}
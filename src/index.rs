@@ -0,0 +1,258 @@
+//! Lookup index for rebuilderd results.
+
+use crate::archweb::ArchwebPackage;
+use crate::package::{Package, Policy, RebuildResult, Status};
+use rebuilderd_common::PkgRelease as RebuilderdPackage;
+use std::collections::HashMap;
+
+/// Index of the packages that are fetched from a rebuilderd instance.
+///
+/// The packages are indexed by their name and by their name/architecture pair.
+/// rebuilderd does not provide the package base so the packages are indexed
+/// by their base only after the names are mapped via [`index_bases`].
+#[derive(Debug, Default)]
+pub struct RebuilderdIndex {
+    /// Address of the rebuilderd instance.
+    rebuilderd: String,
+    /// Packages from rebuilderd.
+    packages: Vec<RebuilderdPackage>,
+    /// Indices of the packages by name.
+    by_name: HashMap<String, Vec<usize>>,
+    /// Indices of the packages by name and architecture.
+    by_arch: HashMap<String, HashMap<String, Vec<usize>>>,
+    /// Indices of the packages by package base.
+    by_base: HashMap<String, Vec<usize>>,
+}
+
+impl RebuilderdIndex {
+    /// Constructs a new index from the packages of the given rebuilderd instance.
+    pub fn new(rebuilderd: &str, packages: Vec<RebuilderdPackage>) -> Self {
+        let mut by_name = HashMap::<String, Vec<usize>>::new();
+        let mut by_arch = HashMap::<String, HashMap<String, Vec<usize>>>::new();
+        for (i, pkg) in packages.iter().enumerate() {
+            by_name.entry(pkg.name.to_string()).or_default().push(i);
+            by_arch
+                .entry(pkg.name.to_string())
+                .or_default()
+                .entry(pkg.architecture.to_string())
+                .or_default()
+                .push(i);
+        }
+        Self {
+            rebuilderd: rebuilderd.to_string(),
+            packages,
+            by_name,
+            by_arch,
+            by_base: HashMap::new(),
+        }
+    }
+
+    /// Indexes the packages by the given package base and name pairs.
+    pub fn index_bases(&mut self, bases: &[(&str, &str)]) {
+        self.by_base.clear();
+        for (pkgbase, pkgname) in bases {
+            if let Some(indices) = self.by_name.get(*pkgname) {
+                self.by_base
+                    .entry(pkgbase.to_string())
+                    .or_default()
+                    .extend(indices);
+            }
+        }
+    }
+
+    /// Returns the packages with the given name.
+    pub fn get_by_name<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a RebuilderdPackage> {
+        self.by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(move |i| &self.packages[*i])
+    }
+
+    /// Returns the packages with the given name and architecture.
+    pub fn get_by_arch<'a>(
+        &'a self,
+        name: &str,
+        arch: &str,
+    ) -> impl Iterator<Item = &'a RebuilderdPackage> {
+        self.by_arch
+            .get(name)
+            .and_then(|by_arch| by_arch.get(arch))
+            .into_iter()
+            .flatten()
+            .map(move |i| &self.packages[*i])
+    }
+
+    /// Returns the packages that are built from the given package base.
+    ///
+    /// The packages are also filtered by architecture if it is given.
+    pub fn get_by_base<'a>(
+        &'a self,
+        pkgbase: &str,
+        arch: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a RebuilderdPackage> {
+        self.by_base
+            .get(pkgbase)
+            .into_iter()
+            .flatten()
+            .map(move |i| &self.packages[*i])
+            .filter(move |pkg| arch.is_none_or(|arch| pkg.architecture == arch))
+    }
+
+    /// Matches the given package data with the rebuilderd results.
+    ///
+    /// The packages are matched by name and architecture (if known)
+    /// and by version unless the version is unknown. If rebuilderd does not
+    /// have a package with the same name (e.g. a split package), the packages
+    /// built from the same package base are matched instead. The status is set to
+    /// [`Status::Stale`] if rebuilderd has a different version of the package
+    /// and [`Status::Unknown`] if there is no match.
+    pub fn get_result(&self, data: &ArchwebPackage) -> RebuildResult {
+        let arch = Some(data.arch.as_str()).filter(|arch| !arch.is_empty() && *arch != "-");
+        let mut candidates = match arch {
+            Some(arch) => self
+                .get_by_arch(&data.pkgname, arch)
+                .collect::<Vec<&RebuilderdPackage>>(),
+            None => self
+                .get_by_name(&data.pkgname)
+                .collect::<Vec<&RebuilderdPackage>>(),
+        };
+        if candidates.is_empty() && !self.by_name.contains_key(&data.pkgname) {
+            candidates = self
+                .get_by_base(&data.pkgbase, arch)
+                .collect::<Vec<&RebuilderdPackage>>();
+        }
+        let version = data.version();
+        let (status, build_id, artifact_url, has_attestation) = match candidates
+            .iter()
+            .find(|p| data.pkgver.is_empty() || p.version == version)
+        {
+            Some(p) => (
+                Status::from(p.status),
+                p.build_id,
+                p.artifact_url.as_str(),
+                p.has_attestation,
+            ),
+            None => match candidates.first() {
+                Some(p) => (Status::Stale, p.build_id, "", false),
+                None => (Status::Unknown, None, "", false),
+            },
+        };
+        RebuildResult {
+            rebuilderd: self.rebuilderd.to_string(),
+            status,
+            build_id: build_id.unwrap_or_default(),
+            artifact_url: artifact_url.to_string(),
+            has_attestation,
+        }
+    }
+}
+
+/// Indexes the packages of all rebuilderd instances by the given
+/// package base and name pairs.
+pub fn index_bases(indexes: &mut [RebuilderdIndex], bases: &[(&str, &str)]) {
+    for index in indexes {
+        index.index_bases(bases);
+    }
+}
+
+/// Matches the given package data with the results of all rebuilderd instances.
+pub fn get_package(indexes: &[RebuilderdIndex], data: ArchwebPackage, policy: Policy) -> Package {
+    let results = indexes
+        .iter()
+        .map(|index| index.get_result(&data))
+        .collect();
+    Package::new(data, results, policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rebuilderd_common::Status as RebuilderdStatus;
+
+    #[test]
+    fn test_rebuilderd_index() {
+        let packages = [
+            ("test", "0.1-1", "x86_64", 1),
+            ("test", "0.1-1", "any", 2),
+            ("xyz", "1:0.2-1", "x86_64", 3),
+        ]
+        .into_iter()
+        .map(|(name, version, arch, build_id)| RebuilderdPackage {
+            status: RebuilderdStatus::Good,
+            build_id: Some(build_id),
+            ..RebuilderdPackage::new(
+                name.to_string(),
+                version.to_string(),
+                String::from("archlinux"),
+                String::from("core"),
+                arch.to_string(),
+                String::new(),
+            )
+        })
+        .collect::<Vec<RebuilderdPackage>>();
+        let index = RebuilderdIndex::new("https://reproducible.archlinux.org", packages);
+        assert_eq!(2, index.get_by_name("test").count());
+        assert_eq!(0, index.get_by_name("abc").count());
+        assert_eq!(
+            Some(Some(2)),
+            index.get_by_arch("test", "any").next().map(|p| p.build_id)
+        );
+        let result = index.get_result(&ArchwebPackage {
+            pkgname: String::from("xyz"),
+            pkgver: String::from("0.3"),
+            pkgrel: String::from("1"),
+            epoch: 1,
+            arch: String::from("x86_64"),
+            ..ArchwebPackage::default()
+        });
+        assert_eq!(Status::Stale, result.status);
+        assert_eq!(3, result.build_id);
+        let result = index.get_result(&ArchwebPackage {
+            pkgname: String::from("xyz"),
+            pkgver: String::from("1:0.2-1"),
+            arch: String::from("any"),
+            ..ArchwebPackage::default()
+        });
+        assert_eq!(Status::Unknown, result.status);
+        assert_eq!(0, result.build_id);
+        let result = index.get_result(&ArchwebPackage {
+            pkgname: String::from("xyz"),
+            ..ArchwebPackage::default()
+        });
+        assert_eq!(Status::Good, result.status);
+        assert_eq!(3, result.build_id);
+        let split_package = ArchwebPackage {
+            pkgname: String::from("xyz-docs"),
+            pkgbase: String::from("xyz"),
+            pkgver: String::from("0.2"),
+            pkgrel: String::from("1"),
+            epoch: 1,
+            arch: String::from("x86_64"),
+            ..ArchwebPackage::default()
+        };
+        assert_eq!(Status::Unknown, index.get_result(&split_package).status);
+        let mut indexes = [index];
+        index_bases(&mut indexes, &[("xyz", "xyz"), ("xyz", "xyz-docs")]);
+        let [index] = indexes;
+        assert_eq!(1, index.get_by_base("xyz", None).count());
+        assert_eq!(0, index.get_by_base("xyz", Some("any")).count());
+        let result = index.get_result(&split_package);
+        assert_eq!(Status::Good, result.status);
+        assert_eq!(3, result.build_id);
+        let package = get_package(
+            &[index],
+            ArchwebPackage {
+                pkgname: String::from("xyz"),
+                pkgver: String::from("1:0.2-1"),
+                arch: String::from("x86_64"),
+                ..ArchwebPackage::default()
+            },
+            Policy::All,
+        );
+        assert_eq!(Status::Good, package.status);
+        assert_eq!(3, package.build_id);
+        assert_eq!("https://reproducible.archlinux.org", package.rebuilderd);
+    }
+}
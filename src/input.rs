@@ -0,0 +1,71 @@
+//! Explicit package inputs.
+
+use crate::archweb::ArchwebPackage;
+use crate::error::ReproStatusError;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Parses the given package list.
+///
+/// Each line consists of a package name and an optional full version
+/// separated by whitespace. Empty lines and comments (`#`) are ignored.
+/// Packages without a version are matched regardless of their version.
+pub fn parse_package_list(contents: &str) -> Vec<ArchwebPackage> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next().map(|name| ArchwebPackage {
+                pkgname: name.to_string(),
+                pkgver: fields.next().unwrap_or_default().to_string(),
+                ..ArchwebPackage::default()
+            })
+        })
+        .collect()
+}
+
+/// Reads the package list from the given path.
+///
+/// The list is read from the standard input if the path is `-`.
+pub fn read_package_list(path: &Path) -> Result<Vec<ArchwebPackage>, ReproStatusError> {
+    log::debug!("reading package list: {:?}", path);
+    let contents = if path == Path::new("-") {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        fs::read_to_string(path)?
+    };
+    Ok(parse_package_list(&contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_package_list() {
+        let packages = parse_package_list(
+            "# ISO manifest\n\
+            base\n\
+            \n\
+            linux 5.17.1.arch1-1\n\
+            git-cliff  0.6.1-1  # comment\n",
+        );
+        assert_eq!(
+            vec![
+                ("base", ""),
+                ("linux", "5.17.1.arch1-1"),
+                ("git-cliff", "0.6.1-1")
+            ],
+            packages
+                .iter()
+                .map(|pkg| (pkg.pkgname.as_str(), pkg.pkgver.as_str()))
+                .collect::<Vec<(&str, &str)>>()
+        );
+        assert_eq!("0.6.1-1", packages[2].version());
+    }
+}
@@ -7,31 +7,146 @@
 
 pub mod archweb;
 pub mod args;
+pub mod attestation;
+pub mod cache;
+pub mod config;
 pub mod error;
 mod fetch;
+pub mod history;
+pub mod index;
+pub mod input;
+pub mod output;
 pub mod package;
+pub mod pacman;
+pub mod pager;
+pub mod stats;
+pub mod verify;
 
 use alpm::{Alpm, Package as AlpmPackage, SigLevel};
 use archweb::ArchwebPackage;
-use args::Args;
+use args::{Args, CacheCommand, OutputFormat, SubCommand, DEFAULT_REBUILDERD};
+use bytesize::ByteSize;
+use cache::{get_cache_dir, CacheEntry, HttpCache, LogMetadata, LogWriter};
+use chrono::Utc;
 use colored::*;
 use console::{Style, Term};
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::{Confirm, Select};
-use error::ReproStatusError;
+use ed25519_dalek::VerifyingKey;
+use error::{ExitCode, ReproStatusError};
 use fetch::*;
-use futures::{executor, future};
-use package::{LogType, Package};
-use rebuilderd_common::Status;
-use reqwest::Client as HttpClient;
+use futures::{executor, future, stream, StreamExt};
+use history::{Change, History, HistoryDiff, HistoryRecord};
+use index::RebuilderdIndex;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use output::{CacheAge, PolicySummary, Summary};
+use package::{LogType, Package, Policy, RebuildResult, Status, Verdict, Verification};
+use pacman::PacmanConfig;
+use pager::Pager;
+use reqwest::{Client as HttpClient, Response, StatusCode};
+use stats::{StatsGroup, StatsSeries};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs;
 use std::io::{self, Write};
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
 /// User agent that will be used for requests.
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
+/// Default timeout (in seconds) of the requests in hook mode.
+const HOOK_TIMEOUT: u64 = 10;
+
+/// Package statuses that are warned about in hook mode.
+const HOOK_WARNING_STATUSES: &[Status] = &[Status::Bad, Status::Unknown];
+
+/// Default package statuses that result in a failure exit code.
+const DEFAULT_FAIL_ON: &[Status] = &[Status::Bad];
+
+/// Maximum number of the concurrent requests while verifying packages.
+const FETCH_CONCURRENCY: usize = 4;
+
+/// Waits for the user to press Enter before returning to the selection dialog.
+fn wait_for_enter() -> Result<(), ReproStatusError> {
+    Confirm::with_theme(&ColorfulTheme {
+        hint_style: Style::new().for_stderr().hidden(),
+        prompt_prefix: console::style("❯".to_string()).for_stderr().green(),
+        prompt_suffix: console::style(String::new()).for_stderr().hidden(),
+        ..ColorfulTheme::default()
+    })
+    .with_prompt("Press Enter to continue")
+    .wait_for_newline(true)
+    .show_default(false)
+    .interact_on_opt(&Term::stderr())?;
+    Ok(())
+}
+
+/// Shows the given cached log via the pager.
+///
+/// The log is decompressed and piped to the standard input of the pager.
+fn view_log(pager: &str, path: &Path) -> Result<(), ReproStatusError> {
+    let mut child = Command::new(pager).stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        match io::copy(&mut cache::read_log(path)?, &mut stdin) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => {
+                result?;
+            }
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Streams the logs in the given response to the cache and the pager.
+///
+/// The pager shows the logs while they are being downloaded and receives them
+/// at its own pace. If it exits before the download is finished, the download
+/// continues into the cache with a progress indicator.
+async fn stream_log(
+    pager: &str,
+    mut response: Response,
+    path: &Path,
+) -> Result<(), ReproStatusError> {
+    let progress = ProgressBar::with_draw_target(
+        response.content_length().unwrap_or_default(),
+        ProgressDrawTarget::hidden(),
+    );
+    progress.set_style(if response.content_length().is_some() {
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} Downloading [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec})",
+            )
+            .progress_chars("=> ")
+    } else {
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} Downloading {bytes} ({bytes_per_sec})")
+    });
+    let mut pager = Pager::spawn(pager)?;
+    let result = async {
+        let mut writer = LogWriter::new(path)?;
+        let mut is_visible = false;
+        while let Some(chunk) = response.chunk().await? {
+            writer.write_all(&chunk)?;
+            progress.inc(chunk.len() as u64);
+            if !is_visible {
+                pager.write_all(&chunk)?;
+                if pager.has_exited()? {
+                    is_visible = true;
+                    progress.set_draw_target(ProgressDrawTarget::stderr());
+                }
+            }
+        }
+        writer.finish()
+    }
+    .await;
+    progress.finish_and_clear();
+    pager.wait()?;
+    result
+}
+
 /// Presents an interactive selection dialog for providing
 /// options for selecting a package and operation.
 ///
@@ -44,10 +159,7 @@ async fn inspect_packages<'a>(
     args: &'a Args,
 ) -> Result<Option<i32>, ReproStatusError> {
     if let Some(filter) = args.filter {
-        packages = packages
-            .into_iter()
-            .filter(|pkg| pkg.status == filter)
-            .collect();
+        packages.retain(|pkg| pkg.status == filter);
     }
     let items = packages
         .iter()
@@ -67,60 +179,107 @@ async fn inspect_packages<'a>(
             .interact_on_opt(&Term::stderr())?;
         if let Some(2) = operation {
             println!("\n{}", packages[index].data);
-            Confirm::with_theme(&ColorfulTheme {
-                hint_style: Style::new().for_stderr().hidden(),
-                prompt_prefix: console::style("❯".to_string()).for_stderr().green(),
-                prompt_suffix: console::style(String::new()).for_stderr().hidden(),
-                ..ColorfulTheme::default()
-            })
-            .with_prompt("Press Enter to continue")
-            .wait_for_newline(true)
-            .show_default(false)
-            .interact_on_opt(&Term::stderr())?;
+            wait_for_enter()?;
             return Ok(Some(index.try_into().unwrap_or_default()));
         }
         let log_type = match operation {
             Some(0) => LogType::Build,
             _ => LogType::Diffoscope,
         };
-        let path = packages[index].get_log_path(log_type, args.cache_dir.as_ref().cloned())?;
+        let mut package = packages[index].clone();
+        let results = package
+            .results
+            .iter()
+            .filter(|result| result.build_id != 0)
+            .collect::<Vec<&RebuildResult>>();
+        if results.len() > 1 {
+            let items = results
+                .iter()
+                .map(|result| format!("{} {}", result.host(), result.status.fancy()))
+                .collect::<Vec<String>>();
+            match Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Select rebuilderd instance")
+                .default(0)
+                .items(&items)
+                .interact_on_opt(&Term::stderr())?
+            {
+                Some(i) => {
+                    package.build_id = results[i].build_id;
+                    package.rebuilderd = results[i].rebuilderd.to_string();
+                }
+                None => return Ok(Some(index.try_into().unwrap_or_default())),
+            }
+        }
+        let path = package.get_log_path(log_type, args.cache_dir.as_ref().cloned())?;
         if path.exists() {
             log::debug!("Hit cache for {:?}", path);
+            view_log(&args.pager, &path)?;
         } else {
-            let logs =
-                fetch_rebuilderd_logs(client, &args.rebuilderd, packages[index].build_id, log_type)
-                    .await?;
-            fs::write(&path, logs)?;
-        }
-        match Command::new(&args.pager).arg(path).spawn() {
-            Ok(mut child) => {
-                child.wait()?;
-                Ok(Some(index.try_into().unwrap_or_default()))
+            match fetch_rebuilderd_logs(client, &package.rebuilderd, package.build_id, log_type)
+                .await
+            {
+                Ok(response) => {
+                    stream_log(&args.pager, response, &path).await?;
+                    LogMetadata {
+                        pkgname: package.data.pkgname.to_string(),
+                        version: package.data.version(),
+                        build_id: package.build_id,
+                        log_type,
+                        url: get_rebuilderd_log_url(
+                            &package.rebuilderd,
+                            package.build_id,
+                            log_type,
+                        ),
+                        fetched_at: Utc::now().timestamp(),
+                    }
+                    .write(&path)?;
+                    let cache_dir = get_cache_dir(args.cache_dir.clone())?;
+                    match cache::enforce_log_size(&cache_dir, args.cache_size.as_u64(), &path) {
+                        Ok(removed) if !removed.is_empty() => log::debug!(
+                            "Removed {} cached log(s) to keep the cache under {}",
+                            removed.len(),
+                            args.cache_size
+                        ),
+                        Ok(_) => {}
+                        Err(e) => log::warn!("Failed to limit the size of the cache: {}", e),
+                    }
+                }
+                Err(e @ ReproStatusError::HttpError { .. }) => {
+                    eprintln!(
+                        "{} {}",
+                        "✘".red(),
+                        match e {
+                            ReproStatusError::HttpError {
+                                status: StatusCode::NOT_FOUND,
+                                ..
+                            } => format!(
+                                "no {} available for this build",
+                                match log_type {
+                                    LogType::Build => "build log",
+                                    LogType::Diffoscope => "diffoscope",
+                                }
+                            ),
+                            e => e.to_string(),
+                        }
+                    );
+                    wait_for_enter()?;
+                    return Ok(Some(index.try_into().unwrap_or_default()));
+                }
+                Err(e) => return Err(e),
             }
-            Err(e) => Err(ReproStatusError::IoError(e)),
         }
+        Ok(Some(index.try_into().unwrap_or_default()))
     } else {
         Ok(None)
     }
 }
 
-/// Prints the status of the packages to the specified output.
-fn print_results<Output: Write>(
-    packages: Vec<Package>,
-    is_local: bool,
-    filter: Option<Status>,
+/// Prints the given packages with their status symbols.
+fn print_packages<'a, Output: Write>(
+    packages: impl Iterator<Item = &'a Package>,
     output: &mut Output,
 ) -> Result<(), ReproStatusError> {
-    let mut negatives = 0;
-    for pkg in &packages {
-        if pkg.status != Status::Good {
-            negatives += 1;
-        }
-        if let Some(filter) = filter {
-            if pkg.status != filter {
-                continue;
-            }
-        }
+    for pkg in packages {
         writeln!(
             output,
             "[{}] {}",
@@ -128,115 +287,987 @@ fn print_results<Output: Write>(
                 Status::Good => "+".green(),
                 Status::Bad => "-".red(),
                 Status::Unknown => "?".yellow(),
+                Status::Stale => "~".blue(),
             },
             pkg
         )?;
     }
+    Ok(())
+}
+
+/// Prints the status of the packages to the specified output.
+///
+/// The age of the cached data is shown if it is used instead of fetching the data.
+fn print_results<Output: Write>(
+    packages: Vec<Package>,
+    args: &Args,
+    cache_age: Option<CacheAge>,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let filter = args.filter;
+    match args.output {
+        OutputFormat::Text => {
+            match args.group_by {
+                Some(group_by) => {
+                    for (i, group) in output::get_groups(&packages, group_by, filter)
+                        .into_iter()
+                        .enumerate()
+                    {
+                        if i != 0 {
+                            writeln!(output)?;
+                        }
+                        writeln!(
+                            output,
+                            "{} {}",
+                            group.name.bold(),
+                            format!(
+                                "({}/{} reproducible, {:.2}%)",
+                                group.summary.reproducible,
+                                group.summary.total,
+                                group.summary.percentage
+                            )
+                            .dimmed()
+                        )?;
+                        print_packages(group.packages.into_iter(), output)?;
+                    }
+                }
+                None => print_packages(
+                    packages
+                        .iter()
+                        .filter(|pkg| output::matches_filter(pkg, filter)),
+                    output,
+                )?,
+            }
+            if let Some(policy) = PolicySummary::new(&packages) {
+                writeln!(output, "{}", policy)?;
+            }
+            if let Some(cache_age) = cache_age {
+                writeln!(output, "{}", cache_age.to_string().dimmed())?;
+            }
+        }
+        OutputFormat::Json => {
+            output::write_json(&packages, filter, args.group_by, cache_age, output)?
+        }
+        OutputFormat::Csv => output::write_delimited(
+            &packages,
+            filter,
+            args.group_by,
+            &args.columns,
+            b',',
+            output,
+        )?,
+        OutputFormat::Tsv => output::write_delimited(
+            &packages,
+            filter,
+            args.group_by,
+            &args.columns,
+            b'\t',
+            output,
+        )?,
+        OutputFormat::Html => output::write_html(
+            &packages,
+            filter,
+            args.group_by,
+            &match &args.maintainer {
+                Some(maintainer) => format!("Reproducibility status of {}'s packages", maintainer),
+                None => String::from("Reproducibility status of the system"),
+            },
+            &args.rebuilderd,
+            cache_age,
+            output,
+        )?,
+    }
     if packages.is_empty() {
         log::warn!("No packages found.")
     } else {
-        match negatives {
+        let summary = Summary::from(packages.as_slice());
+        match summary.unreproducible {
             0 => log::info!("All packages are reproducible!"),
             1 => log::info!(
                 "1/{} package is {} reproducible. Almost there.",
-                packages.len(),
+                summary.total,
                 "not".bold(),
             ),
             _ => log::info!(
                 "{}/{} packages are {} reproducible.",
-                negatives,
-                packages.len(),
+                summary.unreproducible,
+                summary.total,
                 "not".bold(),
             ),
         }
         log::info!(
             "Your {} {:.2}% reproducible.",
-            String::from(if is_local {
+            String::from(if args.maintainer.is_none() {
                 "system is"
             } else {
                 "packages are"
             }),
-            ((packages.len() - negatives) as f64 / packages.len() as f64) * 100.
+            summary.percentage
         )
     }
     Ok(())
 }
 
+/// Returns the exit code based on the statuses that are considered as a failure.
+///
+/// BAD packages take precedence over UNKWN and STALE packages. If the threshold
+/// policy is applied, its verdict replaces the check of BAD packages.
+fn get_exit_code(packages: &[Package], fail_on: &[Status]) -> ExitCode {
+    if packages
+        .iter()
+        .any(|pkg| pkg.verification == Some(Verification::Mismatch))
+    {
+        return ExitCode::MismatchedPackages;
+    }
+    let policy = PolicySummary::new(packages);
+    if policy.as_ref().is_some_and(|policy| !policy.passed) {
+        return ExitCode::PolicyFailure;
+    }
+    [
+        (Status::Bad, ExitCode::BadPackages),
+        (Status::Unknown, ExitCode::UnknownPackages),
+        (Status::Stale, ExitCode::StalePackages),
+    ]
+    .into_iter()
+    .filter(|(status, _)| policy.is_none() || *status != Status::Bad)
+    .find(|(status, _)| {
+        fail_on.contains(status) && packages.iter().any(|pkg| pkg.status == *status)
+    })
+    .map_or(ExitCode::Success, |(_, exit_code)| exit_code)
+}
+
+/// Fetches the packages from the given rebuilderd instances and indexes them.
+///
+/// The instances that cannot be reached are logged and indexed without packages
+/// so that their results are UNKWN. An error is returned if all of them fail.
+async fn fetch_rebuilderd_indexes<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    rebuilders: &'a [String],
+) -> Result<Vec<RebuilderdIndex>, ReproStatusError> {
+    let results = future::join_all(
+        rebuilders
+            .iter()
+            .map(|rebuilder| fetch_rebuilderd_packages(client, cache, rebuilder)),
+    )
+    .await;
+    if results.iter().all(Result::is_err) {
+        if let Some(Err(e)) = results.into_iter().next() {
+            return Err(e);
+        }
+        return Ok(Vec::new());
+    }
+    Ok(rebuilders
+        .iter()
+        .zip(results)
+        .map(|(rebuilder, result)| {
+            RebuilderdIndex::new(
+                rebuilder,
+                result.unwrap_or_else(|e| {
+                    log::warn!("Failed to fetch the packages from {}: {}", rebuilder, e);
+                    Vec::new()
+                }),
+            )
+        })
+        .collect())
+}
+
 /// Returns the reproducibility results of an individual maintainer's packages.
 fn get_maintainer_packages<'a>(
     maintainer: &'a str,
     client: &'a HttpClient,
+    cache: &'a HttpCache,
     args: &'a Args,
 ) -> Result<Vec<Package>, ReproStatusError> {
-    let (archweb, rebuilderd) = executor::block_on(future::try_join(
-        fetch_archweb_packages(client, maintainer),
-        fetch_rebuilderd_packages(client, &args.rebuilderd),
+    let (archweb, mut indexes) = executor::block_on(future::try_join(
+        fetch_archweb_packages(client, cache, maintainer),
+        fetch_rebuilderd_indexes(client, cache, &args.rebuilderd),
     ))?;
-    let mut packages = Vec::new();
-    for pkg in archweb {
-        packages.push(match rebuilderd.iter().find(|p| p.name == pkg.pkgname) {
-            Some(p) => Package {
-                data: pkg,
-                status: p.status,
-                build_id: p.build_id.unwrap_or_default(),
-            },
-            None => Package {
-                data: pkg,
-                status: Status::Unknown,
-                build_id: 0,
-            },
-        })
+    index::index_bases(&mut indexes, &get_archweb_bases(&archweb));
+    Ok(archweb
+        .into_iter()
+        .map(|pkg| index::get_package(&indexes, pkg, args.policy))
+        .collect())
+}
+
+/// Returns the package base and name pairs of the given packages.
+fn get_archweb_bases(packages: &[ArchwebPackage]) -> Vec<(&str, &str)> {
+    packages
+        .iter()
+        .map(|pkg| (pkg.pkgbase.as_str(), pkg.pkgname.as_str()))
+        .collect()
+}
+
+/// Returns the package base and name pairs of the given alpm packages.
+fn get_alpm_bases<'a>(
+    packages: impl IntoIterator<Item = &'a AlpmPackage<'a>>,
+) -> Vec<(&'a str, &'a str)> {
+    packages
+        .into_iter()
+        .map(|pkg| (pkg.base().unwrap_or_else(|| pkg.name()), pkg.name()))
+        .collect()
+}
+
+/// Returns the pacman configuration.
+///
+/// The default configuration is used if it cannot be read.
+fn get_pacman_config(args: &Args) -> PacmanConfig {
+    let path = pacman::get_config_path(args.pacman_conf.clone(), args.root.as_deref());
+    let root = args.root.as_deref().filter(|root| path.starts_with(root));
+    PacmanConfig::load(&path, root).unwrap_or_else(|e| {
+        log::warn!("failed to read {:?}: {}", path, e);
+        PacmanConfig::default()
+    })
+}
+
+/// Initializes alpm and registers the sync databases.
+fn get_alpm(args: &Args) -> Result<Alpm, ReproStatusError> {
+    let options = get_pacman_config(args).get_alpm_options(
+        args.root.as_deref(),
+        args.dbpath.as_deref(),
+        args.repos.clone(),
+    );
+    log::debug!(
+        "querying packages from local database: {} (root: {})",
+        options.db_path,
+        options.root_dir
+    );
+    let pacman = Alpm::new(options.root_dir, options.db_path)?;
+    for repo in &options.repos {
+        log::debug!("registering syncdb: {}", repo);
+        pacman.register_syncdb(repo.to_string(), SigLevel::DATABASE_OPTIONAL)?;
     }
-    Ok(packages)
+    Ok(pacman)
 }
 
 /// Returns the reproducibility results of the locally installed packages.
 fn get_user_packages<'a>(
     client: &'a HttpClient,
+    cache: &'a HttpCache,
     args: &'a Args,
 ) -> Result<Vec<Package>, ReproStatusError> {
-    let rebuilderd = executor::block_on(fetch_rebuilderd_packages(client, &args.rebuilderd))?;
-    log::debug!("querying packages from local database: {}", args.dbpath);
-    let pacman = Alpm::new("/", &args.dbpath)?;
-    for repo in &args.repos {
-        log::debug!("registering syncdb: {}", repo);
-        pacman.register_syncdb(repo.to_string(), SigLevel::DATABASE_OPTIONAL)?;
-    }
+    let mut indexes =
+        executor::block_on(fetch_rebuilderd_indexes(client, cache, &args.rebuilderd))?;
+    let pacman = get_alpm(args)?;
+    let syncpkgs = pacman
+        .syncdbs()
+        .into_iter()
+        .flat_map(|db| db.pkgs().iter().collect::<Vec<AlpmPackage>>())
+        .collect::<Vec<AlpmPackage>>();
+    index::index_bases(&mut indexes, &get_alpm_bases(&syncpkgs));
+    let syncbases = syncpkgs
+        .iter()
+        .filter_map(|pkg| pkg.base())
+        .collect::<HashSet<&str>>();
+    let syncpkgs_by_name = syncpkgs
+        .iter()
+        .map(|pkg| (pkg.name(), pkg))
+        .collect::<HashMap<&str, &AlpmPackage>>();
+    Ok(pacman
+        .localdb()
+        .pkgs()
+        .iter()
+        .filter(|pkg| args.all || pkg.base().is_some_and(|base| syncbases.contains(base)))
+        .map(|pkg| {
+            let mut data = ArchwebPackage::from(*pkg);
+            let syncpkg = syncpkgs_by_name.get(pkg.name());
+            if let Some(db) = syncpkg.and_then(|syncpkg| syncpkg.db()) {
+                data.repo = db.name().to_string();
+            }
+            let mut package = index::get_package(&indexes, data, args.policy);
+            package.published_checksum = syncpkg
+                .filter(|syncpkg| syncpkg.version().as_str() == pkg.version().as_str())
+                .and_then(|syncpkg| syncpkg.sha256sum())
+                .map(String::from);
+            package
+        })
+        .collect())
+}
+
+/// Returns the reproducibility results of the given targets of a transaction.
+///
+/// The version in the sync databases is preferred over the installed version
+/// since the targets are about to be installed or upgraded.
+fn get_target_packages<'a>(
+    targets: &'a [String],
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    args: &'a Args,
+) -> Result<Vec<Package>, ReproStatusError> {
+    let mut indexes =
+        executor::block_on(fetch_rebuilderd_indexes(client, cache, &args.rebuilderd))?;
+    let pacman = get_alpm(args)?;
     let syncdbs = pacman.syncdbs();
     let syncpkgs = syncdbs
-        .into_iter()
+        .iter()
         .flat_map(|db| db.pkgs().iter().collect::<Vec<AlpmPackage>>())
         .collect::<Vec<AlpmPackage>>();
-    let mut packages = Vec::new();
-    for pkg in pacman.localdb().pkgs() {
-        if !args.all && !syncpkgs.iter().any(|p| pkg.base() == p.base()) {
+    index::index_bases(&mut indexes, &get_alpm_bases(&syncpkgs));
+    Ok(targets
+        .iter()
+        .map(|target| {
+            let data = syncdbs
+                .iter()
+                .find_map(|db| db.pkg(target.as_str()).ok())
+                .or_else(|| pacman.localdb().pkg(target.as_str()).ok())
+                .map(|pkg| ArchwebPackage::from(*pkg))
+                .unwrap_or_else(|| ArchwebPackage {
+                    pkgname: target.to_string(),
+                    ..ArchwebPackage::default()
+                });
+            index::get_package(&indexes, data, args.policy)
+        })
+        .collect())
+}
+
+/// Prints a compact warning for each of the targets with the given statuses.
+fn print_hook_warnings<Output: Write>(
+    packages: &[Package],
+    statuses: &[Status],
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    for pkg in packages.iter().filter(|pkg| statuses.contains(&pkg.status)) {
+        writeln!(
+            output,
+            "{} {} {} is {}",
+            "warning:".yellow().bold(),
+            pkg.data.pkgname,
+            pkg.data.version(),
+            pkg.status.fancy().trim_end()
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the package statuses that result in a failure exit code.
+///
+/// Unless they are given, `BAD` and `UNKWN` targets fail in hook mode
+/// and `BAD` packages fail otherwise.
+fn get_fail_on(args: &Args) -> &[Status] {
+    if !args.fail_on.is_empty() {
+        &args.fail_on
+    } else if args.hook {
+        HOOK_WARNING_STATUSES
+    } else {
+        DEFAULT_FAIL_ON
+    }
+}
+
+/// Checks the targets of a transaction that are read from stdin.
+///
+/// Errors are only returned in strict mode so that the transaction is not blocked.
+fn run_hook<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    args: &'a Args,
+) -> Result<ExitCode, ReproStatusError> {
+    let result = io::read_to_string(io::stdin())
+        .map_err(ReproStatusError::from)
+        .and_then(|input| {
+            let targets = input
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect::<Vec<String>>();
+            get_target_packages(&targets, client, cache, args)
+        });
+    let fail_on = get_fail_on(args);
+    match result {
+        Ok(packages) => {
+            let statuses = HOOK_WARNING_STATUSES
+                .iter()
+                .chain(fail_on)
+                .copied()
+                .collect::<Vec<Status>>();
+            print_hook_warnings(&packages, &statuses, &mut io::stdout())?;
+            Ok(if args.strict {
+                get_exit_code(&packages, fail_on)
+            } else {
+                ExitCode::Success
+            })
+        }
+        Err(e) if args.strict => Err(e),
+        Err(e) => {
+            log::warn!("Failed to check the reproducibility status: {}", e);
+            Ok(ExitCode::Success)
+        }
+    }
+}
+
+/// Returns the reproducibility results of the packages in the given
+/// package list and package files.
+fn get_input_packages<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    args: &'a Args,
+) -> Result<Vec<Package>, ReproStatusError> {
+    let mut indexes =
+        executor::block_on(fetch_rebuilderd_indexes(client, cache, &args.rebuilderd))?;
+    let mut packages = match &args.pkglist {
+        Some(path) => input::read_package_list(path)?,
+        None => Vec::new(),
+    };
+    if !args.pkgfile.is_empty() {
+        let db_dir = tempfile::tempdir()?;
+        let pacman = Alpm::new("/", &db_dir.path().to_string_lossy())?;
+        for path in &args.pkgfile {
+            log::debug!("loading package file: {:?}", path);
+            let pkg = pacman.pkg_load(
+                path.to_string_lossy().as_bytes(),
+                false,
+                SigLevel::PACKAGE_OPTIONAL,
+            )?;
+            packages.push(ArchwebPackage::from(*pkg));
+        }
+    }
+    index::index_bases(&mut indexes, &get_archweb_bases(&packages));
+    Ok(packages
+        .into_iter()
+        .map(|pkg| index::get_package(&indexes, pkg, args.policy))
+        .collect())
+}
+
+/// Returns the directories to search for the local package files.
+fn get_package_cache_dirs(args: &Args) -> Vec<PathBuf> {
+    if args.pkg_cache.is_empty() {
+        get_pacman_config(args).get_cache_dirs(args.root.as_deref())
+    } else {
+        args.pkg_cache.clone()
+    }
+}
+
+/// Verifies the local package files of the given packages.
+async fn verify_packages<'a>(
+    packages: &'a mut [Package],
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    args: &'a Args,
+) {
+    let cache_dirs = get_package_cache_dirs(args);
+    let verifications = stream::iter(packages.iter())
+        .map(|pkg| verify::verify_package(client, cache, pkg, &cache_dirs))
+        .buffered(FETCH_CONCURRENCY)
+        .collect::<Vec<Result<Verification, ReproStatusError>>>()
+        .await;
+    for (pkg, verification) in packages.iter_mut().zip(verifications) {
+        pkg.verification = Some(verification.unwrap_or_else(|e| {
+            log::warn!("failed to verify {}: {}", pkg.data.pkgname, e);
+            Verification::Error
+        }));
+    }
+}
+
+/// Returns the number of the trusted rebuilders that signed an attestation
+/// for the given package.
+///
+/// Only the GOOD results with a published attestation are checked. The
+/// attestation must contain the package file with the checksum of the local
+/// package file (or the artifact if it is not found) to be counted.
+async fn get_attested_by<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    package: &'a Package,
+    keys: &'a [VerifyingKey],
+    cache_dirs: &'a [PathBuf],
+) -> usize {
+    let results = package
+        .results
+        .iter()
+        .filter(|result| {
+            result.status == Status::Good && result.build_id != 0 && result.has_attestation
+        })
+        .collect::<Vec<&RebuildResult>>();
+    if results.is_empty() {
+        return 0;
+    }
+    let checksum = match verify::get_package_checksum(client, cache, package, cache_dirs).await {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            log::debug!(
+                "cannot verify the attestations of {}: {}",
+                package.data.pkgname,
+                e
+            );
+            return 0;
+        }
+    };
+    let prefix = verify::get_package_file_prefix(&package.data);
+    let mut signers = HashSet::new();
+    for result in results {
+        match fetch_rebuilderd_attestation(client, cache, &result.rebuilderd, result.build_id)
+            .await
+            .and_then(|attestation| {
+                attestation.verify_product(&prefix, &checksum)?;
+                attestation.get_signers(keys)
+            }) {
+            Ok(keys) => signers.extend(keys),
+            Err(e) => log::debug!(
+                "no valid attestation for {} on {}: {}",
+                package.data.pkgname,
+                result.host(),
+                e
+            ),
+        }
+    }
+    signers.len()
+}
+
+/// Verifies the attestations of the given packages against the trusted keys.
+async fn verify_attestations<'a>(
+    packages: &'a mut [Package],
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    args: &'a Args,
+) -> Result<(), ReproStatusError> {
+    if args.trusted_keys.is_empty() {
+        return Err(ReproStatusError::ConfigError(String::from(
+            "no trusted keys are specified for verifying attestations",
+        )));
+    }
+    let keys = args
+        .trusted_keys
+        .iter()
+        .map(|key| attestation::parse_public_key(key))
+        .collect::<Result<Vec<VerifyingKey>, ReproStatusError>>()?;
+    let cache_dirs = get_package_cache_dirs(args);
+    let attested_by = stream::iter(packages.iter())
+        .map(|pkg| get_attested_by(client, cache, pkg, &keys, &cache_dirs))
+        .buffered(FETCH_CONCURRENCY)
+        .collect::<Vec<usize>>()
+        .await;
+    for (pkg, attested_by) in packages.iter_mut().zip(attested_by) {
+        pkg.attested_by = Some(attested_by);
+    }
+    Ok(())
+}
+
+/// Applies the threshold policy to the given packages.
+fn apply_threshold_policy(
+    packages: &mut [Package],
+    required: usize,
+    args: &Args,
+) -> Result<(), ReproStatusError> {
+    let trusted = match &args.trusted_set {
+        Some(name) => Some(args.trusted_sets.get(name).ok_or_else(|| {
+            ReproStatusError::ConfigError(format!("trusted set not found: `{}`", name))
+        })?),
+        None => None,
+    };
+    let is_queried = |url: &String| {
+        args.rebuilderd.iter().any(|rebuilder| {
+            package::normalize_rebuilderd_url(rebuilder) == package::normalize_rebuilderd_url(url)
+        })
+    };
+    let available = match trusted {
+        Some(trusted) => {
+            for url in trusted.iter().filter(|url| !is_queried(url)) {
+                log::warn!("Trusted rebuilderd instance is not queried: {}", url);
+            }
+            trusted.iter().filter(|url| is_queried(url)).count()
+        }
+        None => args.rebuilderd.len(),
+    };
+    if required > available {
+        log::warn!(
+            "{} rebuilderd instances are required but only {} are available.",
+            required,
+            available
+        );
+    }
+    for pkg in packages.iter_mut() {
+        pkg.verdict = Some(Verdict::new(
+            &pkg.results,
+            required,
+            trusted.map(|v| v.as_slice()),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the name of the checked set of packages to record in the history.
+///
+/// The options that change the checked packages or their statuses are appended
+/// (e.g. `system;all;policy=any`) if they are not the default ones, so that only
+/// the comparable runs are compared.
+fn get_history_target(args: &Args) -> String {
+    let mut target = if let Some(maintainer) = &args.maintainer {
+        format!("maintainer:{}", maintainer)
+    } else if let Some(pkglist) = &args.pkglist {
+        format!("pkglist:{}", pkglist.to_string_lossy())
+    } else if !args.pkgfile.is_empty() {
+        String::from("pkgfile")
+    } else {
+        let mut target = match &args.root {
+            Some(root) => format!("root:{}", root.to_string_lossy()),
+            None => String::from("system"),
+        };
+        if args.all {
+            target.push_str(";all");
+        }
+        target
+    };
+    let rebuilders = args
+        .rebuilderd
+        .iter()
+        .map(|rebuilder| package::normalize_rebuilderd_url(rebuilder))
+        .collect::<Vec<&str>>();
+    if rebuilders != [DEFAULT_REBUILDERD] {
+        target.push_str(&format!(";rebuilderd={}", rebuilders.join(",")));
+    }
+    if args.policy != Policy::All {
+        target.push_str(&format!(
+            ";policy={}",
+            format!("{:?}", args.policy).to_lowercase()
+        ));
+    }
+    target
+}
+
+/// Records the results in the history.
+///
+/// Failures are only logged since the history is not essential for the run.
+fn record_history(packages: &[Package], args: &Args) {
+    if let Err(e) = history::get_history_path().and_then(|path| {
+        History::new(path).append(
+            &HistoryRecord::new(&get_history_target(args), packages),
+            args.history_size,
+        )
+    }) {
+        log::warn!("Failed to record the results in the history: {}", e);
+    }
+}
+
+/// Prints the changes of the packages between two runs.
+fn print_history_diff<Output: Write>(
+    diff: &HistoryDiff,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    writeln!(
+        output,
+        "Changes since {} (until {}):",
+        history::format_timestamp(diff.from),
+        history::format_timestamp(diff.to)
+    )?;
+    for (title, symbol, changes) in [
+        ("Regressions", "-".red(), &diff.regressions),
+        ("Fixes", "+".green(), &diff.fixes),
+        ("Newly unknown", "?".yellow(), &diff.unknown),
+    ] {
+        if changes.is_empty() {
             continue;
         }
-        packages.push(match rebuilderd.iter().find(|p| p.name == pkg.name()) {
-            Some(p) => Package {
-                data: ArchwebPackage::from(pkg),
-                status: p.status,
-                build_id: p.build_id.unwrap_or_default(),
-            },
-            None => Package {
-                data: ArchwebPackage::from(pkg),
-                status: Status::Unknown,
-                build_id: 0,
+        writeln!(output, "{} ({}):", title.bold(), changes.len())?;
+        for change in changes {
+            let Change {
+                name,
+                old_version,
+                new_version,
+                old_status,
+                new_status,
+            } = change;
+            writeln!(
+                output,
+                "[{}] {} {} {}",
+                symbol,
+                name,
+                match old_version {
+                    Some(old_version) if old_version != new_version => {
+                        format!("{} -> {}", old_version, new_version)
+                    }
+                    _ => new_version.to_string(),
+                },
+                match old_status {
+                    Some(old_status) => format!("({} -> {})", old_status, new_status),
+                    None => format!("({}, new package)", new_status),
+                }
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Reports the changes since the previous run or the given date.
+///
+/// Returns [`ExitCode::BadPackages`] if there are regressions.
+fn run_diff(since: Option<i64>, args: &Args) -> Result<ExitCode, ReproStatusError> {
+    let history = History::new(history::get_history_path()?);
+    let records = history.load(&get_history_target(args))?;
+    let (old, new) = match history::get_records_to_compare(&records, since) {
+        Some(records) => records,
+        None => {
+            log::warn!(
+                "Not enough runs are recorded in {:?} to compare.",
+                history.path()
+            );
+            return Ok(ExitCode::Success);
+        }
+    };
+    let diff = HistoryDiff::new(old, new);
+    match args.output {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut io::stdout(), &diff)?;
+            println!();
+        }
+        _ if diff.is_empty() => {
+            log::info!("No changes since {}.", history::format_timestamp(diff.from))
+        }
+        _ => print_history_diff(&diff, &mut io::stdout())?,
+    }
+    Ok(if diff.regressions.is_empty() {
+        ExitCode::Success
+    } else {
+        ExitCode::BadPackages
+    })
+}
+
+/// Prints the statistics with a sparkline per group.
+fn print_stats<Output: Write>(
+    series: &[StatsSeries],
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let width = series
+        .iter()
+        .map(|series| series.name.chars().count())
+        .max()
+        .unwrap_or_default();
+    for series in series {
+        let (first, last) = match (series.points.first(), series.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        let change = last.percentage - first.percentage;
+        writeln!(
+            output,
+            "{:width$}  {}  {:.2}% -> {:.2}% ({}) {}",
+            series.name,
+            stats::get_sparkline(&series.percentages()).bold(),
+            first.percentage,
+            last.percentage,
+            if change > 0. {
+                format!("{:+.2}", change).green()
+            } else if change < 0. {
+                format!("{:+.2}", change).red()
+            } else {
+                String::from("±0.00").normal()
             },
-        });
+            format!(
+                "[{}/{}, {} runs since {}]",
+                last.reproducible,
+                last.total,
+                series.points.len(),
+                history::format_timestamp(first.timestamp)
+            )
+            .dimmed(),
+            width = width,
+        )?;
     }
-    Ok(packages)
+    Ok(())
+}
+
+/// Shows the reproducibility percentage over time.
+///
+/// The records of the checked set of packages are used unless they are
+/// grouped by the target.
+fn run_stats(
+    since: Option<i64>,
+    group: Option<StatsGroup>,
+    args: &Args,
+) -> Result<ExitCode, ReproStatusError> {
+    let history = History::new(history::get_history_path()?);
+    let mut records = match group {
+        Some(StatsGroup::Target) => history.load_all()?,
+        _ => history.load(&get_history_target(args))?,
+    };
+    if let Some(since) = since {
+        records.retain(|record| record.timestamp >= since);
+    }
+    let series = stats::get_series(&records, group);
+    if series.is_empty() {
+        log::warn!("No runs are recorded in {:?}.", history.path());
+        return Ok(ExitCode::Success);
+    }
+    match args.output {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut io::stdout(), &series)?;
+            println!();
+        }
+        OutputFormat::Csv => output::write_stats_delimited(&series, b',', &mut io::stdout())?,
+        OutputFormat::Tsv => output::write_stats_delimited(&series, b'\t', &mut io::stdout())?,
+        _ => print_stats(&series, &mut io::stdout())?,
+    }
+    Ok(ExitCode::Success)
+}
+
+/// Prints the cache entries as a table.
+///
+/// The ages of the entries are calculated at the given Unix timestamp.
+fn print_cache_entries<Output: Write>(
+    entries: &[CacheEntry],
+    now: i64,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            [
+                match entry.log_type {
+                    Some(log_type) => log_type.to_string(),
+                    None if entry.is_partial => String::from("partial"),
+                    None => String::from("http"),
+                },
+                ByteSize(entry.size).to_string(),
+                cache::format_duration(entry.age_at(now)),
+                entry.instance.clone().unwrap_or_else(|| String::from("-")),
+                entry.name.to_string(),
+            ]
+        })
+        .collect::<Vec<[String; 5]>>();
+    let header = ["TYPE", "SIZE", "AGE", "INSTANCE", "NAME"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let format_row = |row: [&str; 5]| {
+        format!(
+            "{:w0$}  {:>w1$}  {:>w2$}  {:w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        )
+    };
+    writeln!(output, "{}", format_row(header).bold())?;
+    for row in &rows {
+        writeln!(output, "{}", format_row(row.each_ref().map(String::as_str)))?;
+    }
+    writeln!(
+        output,
+        "Total: {} in {} entries",
+        ByteSize(entries.iter().map(|entry| entry.size).sum()),
+        entries.len()
+    )?;
+    Ok(())
+}
+
+/// Lists, prunes or clears the cache.
+fn run_cache(command: &CacheCommand, args: &Args) -> Result<ExitCode, ReproStatusError> {
+    let cache_dir = get_cache_dir(args.cache_dir.clone())?;
+    let entries = cache::get_entries(&cache_dir)?;
+    let removed = match command {
+        CacheCommand::List => {
+            match args.output {
+                OutputFormat::Json => {
+                    serde_json::to_writer_pretty(&mut io::stdout(), &entries)?;
+                    println!();
+                }
+                _ => {
+                    print_cache_entries(&entries, Utc::now().timestamp(), &mut io::stdout())?;
+                    let history = History::new(history::get_history_path()?);
+                    println!(
+                        "History: {} in {} records ({:?})",
+                        ByteSize(fs::metadata(history.path()).map_or(0, |v| v.len())),
+                        history.load_all()?.len(),
+                        history.path()
+                    );
+                }
+            }
+            return Ok(ExitCode::Success);
+        }
+        CacheCommand::Prune {
+            max_age,
+            max_size,
+            max_records,
+        } => {
+            if let Some(max_records) = max_records {
+                let history = History::new(history::get_history_path()?);
+                log::info!(
+                    "Removed {} history records from {:?}",
+                    history.prune(Some(*max_records), None)?,
+                    history.path()
+                );
+            }
+            cache::get_prunable_entries(
+                &entries,
+                max_age.map(Duration::from_secs),
+                max_size.map(|max_size| max_size.as_u64()),
+            )
+        }
+        CacheCommand::Clear => entries.iter().collect(),
+    };
+    for entry in &removed {
+        entry.remove()?;
+    }
+    log::info!(
+        "Removed {} entries ({} freed) from {:?}",
+        removed.len(),
+        ByteSize(removed.iter().map(|entry| entry.size).sum()),
+        cache_dir
+    );
+    Ok(ExitCode::Success)
 }
 
 /// Runs `arch-repro-status` and prints the results/shows dialogues.
-pub fn run(args: Args) -> Result<(), ReproStatusError> {
-    let client = HttpClient::builder().user_agent(APP_USER_AGENT).build()?;
-    let packages = if let Some(ref maintainer) = args.maintainer {
-        get_maintainer_packages(maintainer, &client, &args)
+///
+/// Returns the exit code that reflects the reproducibility status.
+pub fn run(mut args: Args) -> Result<ExitCode, ReproStatusError> {
+    args.dedup_rebuilderd();
+    match args.command {
+        Some(SubCommand::Diff { since }) => return run_diff(since, &args),
+        Some(SubCommand::Stats { since, by }) => return run_stats(since, by, &args),
+        Some(SubCommand::Cache { ref command }) => return run_cache(command, &args),
+        None => {}
+    }
+    let mut client = HttpClient::builder().user_agent(APP_USER_AGENT);
+    if let Some(timeout) = args.timeout.or_else(|| args.hook.then_some(HOOK_TIMEOUT)) {
+        client = client.timeout(Duration::from_secs(timeout));
+    }
+    let client = client.build()?;
+    let mut cache = HttpCache::new(
+        get_cache_dir(args.cache_dir.clone())?,
+        Duration::from_secs(args.max_age),
+        args.offline,
+    );
+    if args.hook {
+        cache.fallback = true;
+        return run_hook(&client, &cache, &args);
+    }
+    let mut packages = if let Some(ref maintainer) = args.maintainer {
+        get_maintainer_packages(maintainer, &client, &cache, &args)
+    } else if args.pkglist.is_some() || !args.pkgfile.is_empty() {
+        get_input_packages(&client, &cache, &args)
     } else {
-        get_user_packages(&client, &args)
+        get_user_packages(&client, &cache, &args)
     }?;
+    if args.verify {
+        executor::block_on(verify_packages(&mut packages, &client, &cache, &args));
+    }
+    if let Some(required) = args.require {
+        apply_threshold_policy(&mut packages, required, &args)?;
+    }
+    if args.attest {
+        executor::block_on(verify_attestations(&mut packages, &client, &cache, &args))?;
+    }
+    if !args.no_history && !args.inspect {
+        record_history(&packages, &args);
+    }
     if args.inspect {
+        let cache_dir = get_cache_dir(args.cache_dir.clone())?;
+        match cache::remove_legacy_logs(&cache_dir) {
+            Ok(0) => {}
+            Ok(count) => log::info!(
+                "Removed {} cached log file(s) of the previous cache layout",
+                count
+            ),
+            Err(e) => log::warn!("Failed to remove the legacy cached logs: {}", e),
+        }
         ctrlc::set_handler(move || Term::stdout().show_cursor().expect("failed to show cursor"))?;
         let mut default_selection = Some(0);
         while let Some(selection) = default_selection {
@@ -247,14 +1278,14 @@ pub fn run(args: Args) -> Result<(), ReproStatusError> {
                 &args,
             ))?;
         }
-        Ok(())
+        Ok(ExitCode::Success)
     } else {
-        print_results(
-            packages,
-            args.maintainer.is_none(),
-            args.filter,
-            &mut io::stdout(),
-        )
+        let exit_code = get_exit_code(&packages, get_fail_on(&args));
+        let cache_age = cache
+            .oldest_used()
+            .map(|fetched_at| CacheAge::new(fetched_at, Utc::now().timestamp()));
+        print_results(packages, &args, cache_age, &mut io::stdout())?;
+        Ok(exit_code)
     }
 }
 
@@ -262,7 +1293,9 @@ pub fn run(args: Args) -> Result<(), ReproStatusError> {
 mod tests {
     use super::*;
     use anyhow::Result;
+    use clap::Parser;
     use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
     use std::str;
 
     #[test]
@@ -278,7 +1311,7 @@ mod tests {
                         ..ArchwebPackage::default()
                     },
                     status: Status::Good,
-                    build_id: 0,
+                    ..Package::default()
                 },
                 Package {
                     data: ArchwebPackage {
@@ -288,17 +1321,379 @@ mod tests {
                         ..ArchwebPackage::default()
                     },
                     status: Status::Bad,
-                    build_id: 0,
+                    ..Package::default()
                 },
             ],
-            false,
+            &Args::try_parse_from(["arch-repro-status", "-m", "orhun"])?,
+            Some(CacheAge::new(1_000_000, 1_000_125)),
+            &mut output,
+        )?;
+        assert_eq!(
+            "[+] test 0.1-2 GOOD \n[-] xyz 0.4-1 BAD  \n\
+            Using cached data from 1970-01-12 13:46:40 UTC (2m 5s old).\n",
+            str::from_utf8(&output)?
+        );
+        let mut output = Vec::new();
+        print_results(
+            [
+                ("test", "core", Status::Good),
+                ("xyz", "extra", Status::Bad),
+            ]
+            .into_iter()
+            .map(|(pkgname, repo, status)| Package {
+                data: ArchwebPackage {
+                    pkgname: pkgname.to_string(),
+                    repo: repo.to_string(),
+                    pkgver: String::from("0.1-1"),
+                    ..ArchwebPackage::default()
+                },
+                status,
+                ..Package::default()
+            })
+            .collect(),
+            &Args::try_parse_from(["arch-repro-status", "--group-by", "repo"])?,
             None,
             &mut output,
         )?;
         assert_eq!(
-            "[+] test 0.1-2 GOOD \n[-] xyz 0.4-1 BAD  \n",
+            "core (1/1 reproducible, 100.00%)\n[+] test 0.1-1 GOOD \n\n\
+            extra (0/1 reproducible, 0.00%)\n[-] xyz 0.1-1 BAD  \n",
             str::from_utf8(&output)?
         );
         Ok(())
     }
+
+    #[test]
+    fn test_print_hook_warnings() -> Result<()> {
+        let packages = [("test", Status::Good), ("xyz", Status::Unknown)]
+            .into_iter()
+            .map(|(name, status)| Package {
+                data: ArchwebPackage {
+                    pkgname: name.to_string(),
+                    pkgver: String::from("0.1-1"),
+                    ..ArchwebPackage::default()
+                },
+                status,
+                ..Package::default()
+            })
+            .collect::<Vec<Package>>();
+        let mut output = Vec::new();
+        print_hook_warnings(&packages, HOOK_WARNING_STATUSES, &mut output)?;
+        assert_eq!("warning: xyz 0.1-1 is UNKWN\n", str::from_utf8(&output)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_history_diff() -> Result<()> {
+        let diff = HistoryDiff {
+            from: 0,
+            to: 86400,
+            regressions: vec![Change {
+                name: String::from("test"),
+                old_version: Some(String::from("0.1-1")),
+                new_version: String::from("0.1-2"),
+                old_status: Some(Status::Good),
+                new_status: Status::Bad,
+            }],
+            fixes: Vec::new(),
+            unknown: vec![Change {
+                name: String::from("xyz"),
+                old_version: None,
+                new_version: String::from("0.4-1"),
+                old_status: None,
+                new_status: Status::Unknown,
+            }],
+        };
+        let mut output = Vec::new();
+        print_history_diff(&diff, &mut output)?;
+        assert_eq!(
+            "Changes since 1970-01-01 00:00:00 UTC (until 1970-01-02 00:00:00 UTC):\n\
+            Regressions (1):\n\
+            [-] test 0.1-1 -> 0.1-2 (GOOD -> BAD)\n\
+            Newly unknown (1):\n\
+            [?] xyz 0.4-1 (UNKWN, new package)\n",
+            str::from_utf8(&output)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_stats() -> Result<()> {
+        let series = [("core", [50., 75.]), ("multilib", [100., 100.])]
+            .into_iter()
+            .map(|(name, percentages)| StatsSeries {
+                name: name.to_string(),
+                points: percentages
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, percentage)| stats::StatsPoint {
+                        timestamp: i as i64 * 86400,
+                        total: 4,
+                        reproducible: (percentage / 25.) as usize,
+                        percentage,
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<StatsSeries>>();
+        let mut output = Vec::new();
+        print_stats(&series, &mut output)?;
+        assert_eq!(
+            "core      ▁█  50.00% -> 75.00% (+25.00) \
+            [3/4, 2 runs since 1970-01-01 00:00:00 UTC]\n\
+            multilib  ▅▅  100.00% -> 100.00% (±0.00) \
+            [4/4, 2 runs since 1970-01-01 00:00:00 UTC]\n",
+            str::from_utf8(&output)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_cache_entries() -> Result<()> {
+        let now = 1_000_000;
+        let entries = [
+            CacheEntry {
+                path: PathBuf::from("42_diffoscope.log"),
+                log_type: Some(LogType::Diffoscope),
+                is_partial: false,
+                instance: Some(String::from("reproducible.archlinux.org")),
+                name: String::from("test 0.1-1"),
+                size: 2048,
+                modified: now - 125,
+            },
+            CacheEntry {
+                path: PathBuf::from("x_org.json"),
+                log_type: None,
+                is_partial: false,
+                instance: None,
+                name: String::from("x_org"),
+                size: 10,
+                modified: now - 5,
+            },
+        ];
+        let mut output = Vec::new();
+        print_cache_entries(&entries, now, &mut output)?;
+        assert_eq!(
+            "TYPE          SIZE    AGE  INSTANCE                    NAME\n\
+            diffoscope  2.0 KB  2m 5s  reproducible.archlinux.org  test 0.1-1\n\
+            http          10 B     5s  -                           x_org\n\
+            Total: 2.1 KB in 2 entries\n",
+            str::from_utf8(&output)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_threshold_policy() -> Result<()> {
+        let mut args = Args::try_parse_from([
+            "arch-repro-status",
+            "-r",
+            "https://x.org",
+            "-r",
+            "https://x.org/",
+            "--require",
+            "2",
+        ])?;
+        args.dedup_rebuilderd();
+        assert_eq!(vec![String::from("https://x.org")], args.rebuilderd);
+        let mut packages = vec![Package {
+            results: ["https://x.org", "https://x.org/"]
+                .into_iter()
+                .map(|rebuilderd| RebuildResult {
+                    rebuilderd: rebuilderd.to_string(),
+                    status: Status::Good,
+                    build_id: 1,
+                    artifact_url: String::new(),
+                    has_attestation: false,
+                })
+                .collect(),
+            ..Package::default()
+        }];
+        apply_threshold_policy(&mut packages, 2, &args)?;
+        assert_eq!(
+            Some(Verdict {
+                confirmations: 1,
+                required: 2,
+                passed: false,
+            }),
+            packages[0].verdict
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_history_target() -> Result<()> {
+        let get_target = |args: &[&str]| -> Result<String> {
+            Ok(get_history_target(&Args::try_parse_from(
+                ["arch-repro-status"].iter().chain(args),
+            )?))
+        };
+        assert_eq!("system", get_target(&[])?);
+        assert_eq!("maintainer:orhun", get_target(&["-m", "orhun", "-a"])?);
+        assert_eq!("root:/mnt;all", get_target(&["--root", "/mnt", "-a"])?);
+        assert_eq!(
+            "system;rebuilderd=https://reproducible.archlinux.org,https://x.org;policy=any",
+            get_target(&[
+                "-r",
+                "https://reproducible.archlinux.org/,https://x.org",
+                "--policy",
+                "any"
+            ])?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rebuilderd_indexes() -> Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let cache = HttpCache::new(cache_dir.path().to_path_buf(), Duration::ZERO, true);
+        cache.write(&cache::CachedResponse::new(
+            "https://reproducible.archlinux.org/api/v0/pkgs/list?distro=archlinux",
+            String::from("[]"),
+            None,
+            None,
+        ))?;
+        let client = HttpClient::new();
+        let rebuilders = vec![
+            String::from("https://reproducible.archlinux.org"),
+            String::from("https://rebuilder.example.com"),
+        ];
+        assert_eq!(
+            2,
+            fetch_rebuilderd_indexes(&client, &cache, &rebuilders)
+                .await?
+                .len()
+        );
+        assert!(fetch_rebuilderd_indexes(&client, &cache, &rebuilders[1..])
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_fail_on() -> Result<()> {
+        for arg in ["--verify", "--require=2", "--attest", "--output=json"] {
+            assert!(Args::try_parse_from(["arch-repro-status", "--hook", arg]).is_err());
+        }
+        assert_eq!(
+            HOOK_WARNING_STATUSES,
+            get_fail_on(&Args::try_parse_from(["arch-repro-status", "--hook"])?)
+        );
+        assert_eq!(
+            &[Status::Stale],
+            get_fail_on(&Args::try_parse_from([
+                "arch-repro-status",
+                "--hook",
+                "--strict",
+                "--fail-on",
+                "STALE"
+            ])?)
+        );
+        assert_eq!(
+            DEFAULT_FAIL_ON,
+            get_fail_on(&Args::try_parse_from(["arch-repro-status"])?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_exit_code() {
+        let packages = [Status::Good, Status::Unknown, Status::Bad]
+            .into_iter()
+            .map(|status| Package {
+                status,
+                ..Package::default()
+            })
+            .collect::<Vec<Package>>();
+        assert_eq!(ExitCode::Success, get_exit_code(&packages, &[]));
+        assert_eq!(
+            ExitCode::Success,
+            get_exit_code(&packages[..1], &[Status::Bad])
+        );
+        assert_eq!(
+            ExitCode::BadPackages,
+            get_exit_code(&packages, &[Status::Bad, Status::Unknown])
+        );
+        assert_eq!(
+            ExitCode::UnknownPackages,
+            get_exit_code(&packages[..2], &[Status::Bad, Status::Unknown])
+        );
+        assert_eq!(
+            ExitCode::Success,
+            get_exit_code(&packages[..2], &[Status::Bad])
+        );
+        assert_eq!(
+            ExitCode::StalePackages,
+            get_exit_code(
+                &[Package {
+                    status: Status::Stale,
+                    ..Package::default()
+                }],
+                &[Status::Bad, Status::Stale]
+            )
+        );
+        assert_eq!(
+            ExitCode::PolicyFailure,
+            get_exit_code(
+                &[Package {
+                    status: Status::Good,
+                    verdict: Some(Verdict {
+                        confirmations: 1,
+                        required: 2,
+                        passed: false,
+                    }),
+                    ..Package::default()
+                }],
+                &[Status::Bad]
+            )
+        );
+        let verdict = |passed| Verdict {
+            confirmations: 1,
+            required: 1,
+            passed,
+        };
+        assert_eq!(
+            ExitCode::Success,
+            get_exit_code(
+                &[Package {
+                    status: Status::Bad,
+                    verdict: Some(verdict(true)),
+                    ..Package::default()
+                }],
+                &[Status::Bad]
+            )
+        );
+        assert_eq!(
+            ExitCode::UnknownPackages,
+            get_exit_code(
+                &[Status::Good, Status::Unknown, Status::Stale].map(|status| Package {
+                    status,
+                    verdict: Some(verdict(true)),
+                    ..Package::default()
+                }),
+                &[Status::Unknown, Status::Stale]
+            )
+        );
+        assert_eq!(
+            ExitCode::PolicyFailure,
+            get_exit_code(
+                &[Package {
+                    status: Status::Unknown,
+                    verdict: Some(verdict(false)),
+                    ..Package::default()
+                }],
+                &[Status::Unknown]
+            )
+        );
+        assert_eq!(
+            ExitCode::MismatchedPackages,
+            get_exit_code(
+                &[Package {
+                    status: Status::Good,
+                    verification: Some(Verification::Mismatch),
+                    ..Package::default()
+                }],
+                &[Status::Bad]
+            )
+        );
+    }
 }
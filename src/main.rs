@@ -1,19 +1,27 @@
 use arch_repro_status::args::Args;
-use clap::Parser;
+use arch_repro_status::error::ExitCode;
 use log::Level;
 use std::env;
+use std::process;
 
 /// Levels of logging.
 const LOG_LEVELS: &[Level] = &[Level::Warn, Level::Info, Level::Debug, Level::Trace];
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let args = Args::parse_with_config().unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(ExitCode::from(&e) as i32)
+    });
     if env::var_os("RUST_LOG").is_none() {
         let level_index = ((args.verbose + 1) * !args.quiet as u8) as usize;
         let level = LOG_LEVELS.get(level_index).unwrap_or(&Level::Trace);
         env::set_var("RUST_LOG", level.as_str());
     }
     pretty_env_logger::init();
-    arch_repro_status::run(args).unwrap_or_else(|e| log::error!("{}", e))
+    let exit_code = arch_repro_status::run(args).unwrap_or_else(|e| {
+        log::error!("{}", e);
+        ExitCode::from(&e)
+    });
+    process::exit(exit_code as i32)
 }
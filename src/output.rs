@@ -0,0 +1,883 @@
+//! Machine-readable output formats.
+
+use crate::args::{Column, GroupBy};
+use crate::cache::format_duration;
+use crate::error::ReproStatusError;
+use crate::fetch::get_rebuilderd_log_url;
+use crate::history;
+use crate::package::{LogType, Package, Status, Verdict};
+use crate::stats::StatsSeries;
+use colored::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::time::Duration;
+
+/// Version of the JSON document format.
+///
+/// It is incremented when a field is removed or its meaning changes.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// Summary of the reproducibility results.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Summary {
+    /// Total number of packages.
+    pub total: usize,
+    /// Number of reproducible packages.
+    pub reproducible: usize,
+    /// Number of packages that are not reproducible or have an unknown status.
+    pub unreproducible: usize,
+    /// Percentage of the reproducible packages.
+    pub percentage: f64,
+}
+
+impl<'a> FromIterator<&'a Package> for Summary {
+    fn from_iter<I: IntoIterator<Item = &'a Package>>(packages: I) -> Self {
+        let (total, reproducible) =
+            packages
+                .into_iter()
+                .fold((0, 0), |(total, reproducible), pkg| {
+                    (
+                        total + 1,
+                        reproducible + usize::from(pkg.status == Status::Good),
+                    )
+                });
+        Self {
+            total,
+            reproducible,
+            unreproducible: total - reproducible,
+            percentage: if total == 0 {
+                0.
+            } else {
+                (reproducible as f64 / total as f64) * 100.
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a [Package]> for Summary {
+    fn from(packages: &'a [Package]) -> Self {
+        packages.iter().collect()
+    }
+}
+
+/// Age of the cached data that is used instead of fetching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct CacheAge {
+    /// Unix timestamp of the time that the oldest cached data is fetched.
+    pub fetched_at: i64,
+    /// Age of the oldest cached data in seconds.
+    pub age: u64,
+}
+
+impl CacheAge {
+    /// Constructs a new instance from the fetch time and the current timestamp.
+    pub fn new(fetched_at: i64, now: i64) -> Self {
+        Self {
+            fetched_at,
+            age: (now - fetched_at).max(0) as u64,
+        }
+    }
+}
+
+impl fmt::Display for CacheAge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Using cached data from {} ({} old).",
+            history::format_timestamp(self.fetched_at),
+            format_duration(Duration::from_secs(self.age))
+        )
+    }
+}
+
+/// Packages that share the same value of a field.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Group<'a> {
+    /// Value of the field.
+    pub name: String,
+    /// Summary of all the packages in the group (regardless of the filter).
+    pub summary: Summary,
+    /// Packages in the group that match the filter.
+    #[serde(skip)]
+    pub packages: Vec<&'a Package>,
+}
+
+/// Returns the values of the given field for a package.
+///
+/// Unknown values are represented as `-`.
+fn get_group_names(pkg: &Package, group_by: GroupBy) -> Vec<String> {
+    let names = match group_by {
+        GroupBy::Repo => vec![pkg.data.repo.to_string()],
+        GroupBy::Maintainer => pkg.data.maintainers.clone(),
+        GroupBy::Packager => vec![pkg.data.packager.to_string()],
+        GroupBy::Pkgbase => vec![pkg.data.pkgbase.to_string()],
+        GroupBy::Arch => vec![pkg.data.arch.to_string()],
+    };
+    if names.iter().all(|name| name.is_empty()) {
+        vec![String::from("-")]
+    } else {
+        names.into_iter().filter(|name| !name.is_empty()).collect()
+    }
+}
+
+/// Returns the groups of the given packages sorted by name.
+///
+/// Packages with multiple values (e.g. maintainers) are included in each group.
+/// Only the groups that have packages matching the filter are returned.
+pub fn get_groups(
+    packages: &[Package],
+    group_by: GroupBy,
+    filter: Option<Status>,
+) -> Vec<Group<'_>> {
+    let mut groups = BTreeMap::<String, Vec<&Package>>::new();
+    for pkg in packages {
+        for name in get_group_names(pkg, group_by) {
+            groups.entry(name).or_default().push(pkg);
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(name, packages)| Group {
+            name,
+            summary: packages.iter().copied().collect(),
+            packages: packages
+                .into_iter()
+                .filter(|pkg| matches_filter(pkg, filter))
+                .collect(),
+        })
+        .filter(|group| !group.packages.is_empty())
+        .collect()
+}
+
+/// Returns `true` if the package matches the given status filter.
+pub fn matches_filter(pkg: &Package, filter: Option<Status>) -> bool {
+    filter.is_none() || filter == Some(pkg.status)
+}
+
+/// Returns the packages that match the filter along with the names of their groups.
+///
+/// Each package is returned once for each of its groups if the field is given.
+fn get_rows(
+    packages: &[Package],
+    filter: Option<Status>,
+    group_by: Option<GroupBy>,
+) -> Vec<(Option<String>, &Package)> {
+    match group_by {
+        Some(group_by) => get_groups(packages, group_by, filter)
+            .into_iter()
+            .flat_map(|group| {
+                let name = group.name;
+                group
+                    .packages
+                    .into_iter()
+                    .map(move |pkg| (Some(name.to_string()), pkg))
+            })
+            .collect(),
+        None => packages
+            .iter()
+            .filter(|pkg| matches_filter(pkg, filter))
+            .map(|pkg| (None, pkg))
+            .collect(),
+    }
+}
+
+/// Summary of the threshold policy verdicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct PolicySummary {
+    /// Number of the required confirmations.
+    pub required: usize,
+    /// Number of packages that satisfy the policy.
+    pub confirmed: usize,
+    /// Number of packages that do not satisfy the policy.
+    pub unconfirmed: usize,
+    /// Whether if all of the packages satisfy the policy.
+    pub passed: bool,
+}
+
+impl PolicySummary {
+    /// Constructs a new instance from the verdicts of the given packages.
+    ///
+    /// Returns `None` if the threshold policy is not applied.
+    pub fn new(packages: &[Package]) -> Option<Self> {
+        let verdicts = packages
+            .iter()
+            .filter_map(|pkg| pkg.verdict)
+            .collect::<Vec<Verdict>>();
+        let required = verdicts.first()?.required;
+        let confirmed = verdicts.iter().filter(|verdict| verdict.passed).count();
+        Some(Self {
+            required,
+            confirmed,
+            unconfirmed: verdicts.len() - confirmed,
+            passed: confirmed == verdicts.len(),
+        })
+    }
+}
+
+impl fmt::Display for PolicySummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Policy: {} ({}/{} packages are confirmed by at least {} rebuilder{})",
+            if self.passed {
+                "PASS".green().bold()
+            } else {
+                "FAIL".red().bold()
+            },
+            self.confirmed,
+            self.confirmed + self.unconfirmed,
+            self.required,
+            if self.required == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// JSON document that contains the results.
+#[derive(Debug, serde::Serialize)]
+struct JsonReport<'a> {
+    /// Version of the document format.
+    version: u32,
+    /// Summary of all the packages (regardless of the filter).
+    summary: Summary,
+    /// Summary of the threshold policy verdicts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    policy: Option<PolicySummary>,
+    /// Age of the cached data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache: Option<CacheAge>,
+    /// Summaries of the groups that have packages matching the filter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<Group<'a>>>,
+    /// Packages that match the filter.
+    packages: Vec<&'a Package>,
+}
+
+/// Writes the packages and their summary as a JSON document.
+///
+/// The summary of each group is included if the field to group by is given.
+pub fn write_json<Output: Write>(
+    packages: &[Package],
+    filter: Option<Status>,
+    group_by: Option<GroupBy>,
+    cache_age: Option<CacheAge>,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let report = JsonReport {
+        version: JSON_FORMAT_VERSION,
+        summary: Summary::from(packages),
+        policy: PolicySummary::new(packages),
+        cache: cache_age,
+        groups: group_by.map(|group_by| get_groups(packages, group_by, filter)),
+        packages: packages
+            .iter()
+            .filter(|pkg| matches_filter(pkg, filter))
+            .collect(),
+    };
+    serde_json::to_writer_pretty(&mut *output, &report)?;
+    writeln!(output)?;
+    Ok(())
+}
+
+/// Returns the value of the given column for a package.
+fn get_column_value(pkg: &Package, column: Column) -> String {
+    match column {
+        Column::Pkgname => pkg.data.pkgname.to_string(),
+        Column::Pkgbase => pkg.data.pkgbase.to_string(),
+        Column::Version => pkg.data.version(),
+        Column::Repo => pkg.data.repo.to_string(),
+        Column::Arch => pkg.data.arch.to_string(),
+        Column::Status => pkg.status.to_string(),
+        Column::BuildId => pkg.build_id.to_string(),
+        Column::Packager => pkg.data.packager.to_string(),
+        Column::BuildDate => pkg.data.build_date.to_string(),
+        Column::Maintainers => pkg.data.maintainers.join(" "),
+        Column::Results => pkg
+            .results
+            .iter()
+            .map(|result| format!("{}={}", result.host(), result.status))
+            .collect::<Vec<String>>()
+            .join(" "),
+        Column::Verification => pkg
+            .verification
+            .map(|verification| verification.to_string())
+            .unwrap_or_default(),
+        Column::Verdict => pkg
+            .verdict
+            .map(|verdict| String::from(if verdict.passed { "PASS" } else { "FAIL" }))
+            .unwrap_or_default(),
+        Column::AttestedBy => pkg
+            .attested_by
+            .map(|attested_by| attested_by.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Writes the packages as delimiter-separated values (e.g. CSV/TSV).
+///
+/// The first row contains the names of the columns.
+pub fn write_delimited<Output: Write>(
+    packages: &[Package],
+    filter: Option<Status>,
+    group_by: Option<GroupBy>,
+    columns: &[Column],
+    delimiter: u8,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(output);
+    writer.write_record(
+        group_by
+            .map(|_| "group")
+            .into_iter()
+            .chain(columns.iter().map(Column::name)),
+    )?;
+    for (group, pkg) in get_rows(packages, filter, group_by) {
+        writer.write_record(
+            group
+                .into_iter()
+                .chain(columns.iter().map(|column| get_column_value(pkg, *column))),
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the statistics as delimiter-separated values (e.g. CSV/TSV).
+///
+/// Each row contains the result of a group in a run.
+pub fn write_stats_delimited<Output: Write>(
+    series: &[StatsSeries],
+    delimiter: u8,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(output);
+    writer.write_record([
+        "name",
+        "timestamp",
+        "date",
+        "total",
+        "reproducible",
+        "percentage",
+    ])?;
+    for series in series {
+        for point in &series.points {
+            writer.write_record([
+                series.name.to_string(),
+                point.timestamp.to_string(),
+                history::format_timestamp(point.timestamp),
+                point.total.to_string(),
+                point.reproducible.to_string(),
+                format!("{:.2}", point.percentage),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Style sheet and script of the HTML report.
+const HTML_HEAD: &str = r#"<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; text-align: left; }
+th { cursor: pointer; user-select: none; background: #f4f4f4; }
+th.asc::after { content: " \25B2"; }
+th.desc::after { content: " \25BC"; }
+.GOOD { color: #1a7f37; font-weight: bold; }
+.BAD { color: #cf222e; font-weight: bold; }
+.UNKWN { color: #9a6700; font-weight: bold; }
+.STALE { color: #0969da; font-weight: bold; }
+tr.disagreement { background: #fff8c5; }
+#controls { margin: 1em 0; }
+</style>
+<script>
+document.addEventListener("DOMContentLoaded", function () {
+  const table = document.getElementById("packages");
+  const body = table.tBodies[0];
+  const search = document.getElementById("search");
+  const status = document.getElementById("status");
+  function filter() {
+    const query = search.value.toLowerCase();
+    for (const row of body.rows) {
+      const matches = row.textContent.toLowerCase().includes(query) &&
+        (status.value === "" || row.dataset.status === status.value);
+      row.hidden = !matches;
+    }
+  }
+  search.addEventListener("input", filter);
+  status.addEventListener("change", filter);
+  table.querySelectorAll("th").forEach(function (header, index) {
+    header.addEventListener("click", function () {
+      const ascending = !header.classList.contains("asc");
+      table.querySelectorAll("th").forEach(function (th) {
+        th.classList.remove("asc", "desc");
+      });
+      header.classList.add(ascending ? "asc" : "desc");
+      const rows = Array.from(body.rows);
+      rows.sort(function (a, b) {
+        const x = a.cells[index].textContent;
+        const y = b.cells[index].textContent;
+        return (ascending ? 1 : -1) * x.localeCompare(y, undefined, { numeric: true });
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+</script>"#;
+
+/// Escapes the special characters for using the given text in HTML.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes the packages as a self-contained HTML report.
+///
+/// Packages that have different results from the rebuilderd instances are highlighted.
+pub fn write_html<Output: Write>(
+    packages: &[Package],
+    filter: Option<Status>,
+    group_by: Option<GroupBy>,
+    title: &str,
+    rebuilders: &[String],
+    cache_age: Option<CacheAge>,
+    output: &mut Output,
+) -> Result<(), ReproStatusError> {
+    let summary = Summary::from(packages);
+    let title = escape_html(title);
+    writeln!(output, "<!DOCTYPE html>")?;
+    writeln!(output, "<html lang=\"en\">")?;
+    writeln!(output, "<head>")?;
+    writeln!(output, "<meta charset=\"utf-8\">")?;
+    writeln!(output, "<title>{}</title>", title)?;
+    writeln!(output, "{}", HTML_HEAD)?;
+    writeln!(output, "</head>")?;
+    writeln!(output, "<body>")?;
+    writeln!(output, "<h1>{}</h1>", title)?;
+    writeln!(
+        output,
+        "<p>{}/{} packages are reproducible (<strong>{:.2}%</strong>).</p>",
+        summary.reproducible, summary.total, summary.percentage
+    )?;
+    writeln!(
+        output,
+        "<p>Generated by {} at {} using data from {}.</p>",
+        env!("CARGO_PKG_NAME"),
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S %Z"),
+        rebuilders
+            .iter()
+            .map(|rebuilder| {
+                let rebuilder = escape_html(rebuilder);
+                format!("<a href=\"{0}\">{0}</a>", rebuilder)
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    )?;
+    if let Some(cache_age) = cache_age {
+        writeln!(output, "<p>{}</p>", cache_age)?;
+    }
+    writeln!(output, "<div id=\"controls\">")?;
+    writeln!(
+        output,
+        "<input id=\"search\" type=\"search\" placeholder=\"Search packages\">"
+    )?;
+    writeln!(output, "<select id=\"status\">")?;
+    writeln!(output, "<option value=\"\">All statuses</option>")?;
+    for status in [Status::Good, Status::Bad, Status::Unknown, Status::Stale] {
+        writeln!(output, "<option>{}</option>", status)?;
+    }
+    writeln!(output, "</select>")?;
+    writeln!(output, "</div>")?;
+    writeln!(output, "<table id=\"packages\">")?;
+    writeln!(output, "<thead>")?;
+    writeln!(
+        output,
+        "<tr>{}<th>Name</th><th>Version</th><th>Repository</th><th>Architecture</th>\
+        <th>Status</th><th>Rebuilders</th><th>Build log</th><th>Diffoscope</th></tr>",
+        if group_by.is_some() {
+            "<th>Group</th>"
+        } else {
+            ""
+        }
+    )?;
+    writeln!(output, "</thead>")?;
+    writeln!(output, "<tbody>")?;
+    for (group, pkg) in get_rows(packages, filter, group_by) {
+        let name = escape_html(&pkg.data.pkgname);
+        let log_link = |log_type: LogType| {
+            format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(&get_rebuilderd_log_url(
+                    &pkg.rebuilderd,
+                    pkg.build_id,
+                    log_type
+                )),
+                log_type
+            )
+        };
+        writeln!(
+            output,
+            "<tr data-status=\"{status}\"{}>{}<td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+            <td class=\"{status}\">{status}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            if pkg.has_disagreement() {
+                " class=\"disagreement\""
+            } else {
+                ""
+            },
+            match group {
+                Some(group) => format!("<td>{}</td>", escape_html(&group)),
+                None => String::new(),
+            },
+            match pkg.data.package_url() {
+                Some(url) => format!("<a href=\"{}\">{}</a>", escape_html(&url), name),
+                None => name,
+            },
+            escape_html(&pkg.data.version()),
+            escape_html(&pkg.data.repo),
+            escape_html(&pkg.data.arch),
+            pkg.results
+                .iter()
+                .map(|result| format!(
+                    "{}: <span class=\"{status}\">{status}</span>",
+                    escape_html(&result.host()),
+                    status = result.status
+                ))
+                .collect::<Vec<String>>()
+                .join("<br>"),
+            if pkg.build_id != 0 {
+                log_link(LogType::Build)
+            } else {
+                String::new()
+            },
+            if pkg.build_id != 0 && pkg.status == Status::Bad {
+                log_link(LogType::Diffoscope)
+            } else {
+                String::new()
+            },
+            status = pkg.status,
+        )?;
+    }
+    writeln!(output, "</tbody>")?;
+    writeln!(output, "</table>")?;
+    writeln!(output, "</body>")?;
+    writeln!(output, "</html>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archweb::ArchwebPackage;
+    use crate::package::RebuildResult;
+    use crate::stats::StatsPoint;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    #[test]
+    fn test_write_json() -> Result<()> {
+        let packages = vec![
+            Package {
+                data: ArchwebPackage {
+                    pkgname: String::from("test"),
+                    ..ArchwebPackage::default()
+                },
+                status: Status::Good,
+                build_id: 1,
+                ..Package::default()
+            },
+            Package {
+                data: ArchwebPackage {
+                    pkgname: String::from("xyz"),
+                    ..ArchwebPackage::default()
+                },
+                status: Status::Bad,
+                build_id: 2,
+                ..Package::default()
+            },
+        ];
+        let mut output = Vec::new();
+        write_json(
+            &packages,
+            Some(Status::Bad),
+            None,
+            Some(CacheAge::new(1_000_000, 1_007_500)),
+            &mut output,
+        )?;
+        let report: Value = serde_json::from_slice(&output)?;
+        assert_eq!(1, report["version"]);
+        assert_eq!(2, report["summary"]["total"]);
+        assert_eq!(1, report["summary"]["reproducible"]);
+        assert_eq!(1, report["summary"]["unreproducible"]);
+        assert_eq!(50., report["summary"]["percentage"]);
+        assert_eq!(1, report["packages"].as_array().map_or(0, Vec::len));
+        assert_eq!("xyz", report["packages"][0]["data"]["pkgname"]);
+        assert_eq!("BAD", report["packages"][0]["status"]);
+        assert_eq!(2, report["packages"][0]["build_id"]);
+        assert!(report.get("policy").is_none());
+        assert!(report.get("groups").is_none());
+        assert_eq!(1_000_000, report["cache"]["fetched_at"]);
+        assert_eq!(7500, report["cache"]["age"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_groups() -> Result<()> {
+        let packages = [
+            ("test", vec!["orhun", "nuhro"], Status::Good),
+            ("xyz", vec!["orhun"], Status::Bad),
+            ("abc", vec![], Status::Unknown),
+        ]
+        .into_iter()
+        .map(|(pkgname, maintainers, status)| Package {
+            data: ArchwebPackage {
+                pkgname: pkgname.to_string(),
+                repo: String::from("extra"),
+                maintainers: maintainers.into_iter().map(String::from).collect(),
+                ..ArchwebPackage::default()
+            },
+            status,
+            ..Package::default()
+        })
+        .collect::<Vec<Package>>();
+        let groups = get_groups(&packages, GroupBy::Maintainer, None);
+        assert_eq!(
+            vec![("-", 1, 0), ("nuhro", 1, 1), ("orhun", 2, 1)],
+            groups
+                .iter()
+                .map(|group| (
+                    group.name.as_str(),
+                    group.summary.total,
+                    group.summary.reproducible
+                ))
+                .collect::<Vec<(&str, usize, usize)>>()
+        );
+        assert_eq!(50., groups[2].summary.percentage);
+        assert_eq!(1, get_groups(&packages, GroupBy::Repo, None).len());
+        let groups = get_groups(&packages, GroupBy::Maintainer, Some(Status::Bad));
+        assert_eq!(
+            vec![("orhun", 2, 1)],
+            groups
+                .iter()
+                .map(|group| (
+                    group.name.as_str(),
+                    group.summary.total,
+                    group.packages.len()
+                ))
+                .collect::<Vec<(&str, usize, usize)>>()
+        );
+        let mut output = Vec::new();
+        write_json(&packages, None, Some(GroupBy::Arch), None, &mut output)?;
+        let report: Value = serde_json::from_slice(&output)?;
+        assert_eq!("-", report["groups"][0]["name"]);
+        assert_eq!(3, report["groups"][0]["summary"]["total"]);
+        assert!(report["groups"][0].get("packages").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_summary() {
+        let mut packages = [true, false, true]
+            .into_iter()
+            .map(|passed| Package {
+                verdict: Some(Verdict {
+                    confirmations: if passed { 2 } else { 1 },
+                    required: 2,
+                    passed,
+                }),
+                ..Package::default()
+            })
+            .collect::<Vec<Package>>();
+        assert_eq!(
+            Some(PolicySummary {
+                required: 2,
+                confirmed: 2,
+                unconfirmed: 1,
+                passed: false,
+            }),
+            PolicySummary::new(&packages)
+        );
+        packages.remove(1);
+        assert_eq!(Some(true), PolicySummary::new(&packages).map(|p| p.passed));
+        assert_eq!(None, PolicySummary::new(&[Package::default()]));
+    }
+
+    #[test]
+    fn test_write_delimited() -> Result<()> {
+        let packages = vec![
+            Package {
+                data: ArchwebPackage {
+                    pkgname: String::from("test"),
+                    pkgver: String::from("0.1"),
+                    pkgrel: String::from("2"),
+                    maintainers: vec![String::from("orhun"), String::from("nuhro")],
+                    packager: String::from("Orhun <orhun@archlinux.org>"),
+                    ..ArchwebPackage::default()
+                },
+                status: Status::Good,
+                build_id: 1,
+                ..Package::default()
+            },
+            Package {
+                data: ArchwebPackage {
+                    pkgname: String::from("xyz"),
+                    pkgver: String::from("1:0.4-1"),
+                    packager: String::from("\"xyz\", the packager"),
+                    ..ArchwebPackage::default()
+                },
+                status: Status::Bad,
+                build_id: 2,
+                ..Package::default()
+            },
+        ];
+        let columns = [
+            Column::Pkgname,
+            Column::Version,
+            Column::Status,
+            Column::Packager,
+            Column::Maintainers,
+        ];
+        let mut output = Vec::new();
+        write_delimited(&packages, None, None, &columns, b',', &mut output)?;
+        assert_eq!(
+            "pkgname,version,status,packager,maintainers\n\
+            test,0.1-2,GOOD,Orhun <orhun@archlinux.org>,orhun nuhro\n\
+            xyz,1:0.4-1,BAD,\"\"\"xyz\"\", the packager\",\n",
+            String::from_utf8(output)?
+        );
+        let mut output = Vec::new();
+        write_delimited(
+            &packages,
+            Some(Status::Good),
+            None,
+            &columns[..3],
+            b'\t',
+            &mut output,
+        )?;
+        assert_eq!(
+            "pkgname\tversion\tstatus\ntest\t0.1-2\tGOOD\n",
+            String::from_utf8(output)?
+        );
+        let mut output = Vec::new();
+        write_delimited(
+            &packages,
+            None,
+            Some(GroupBy::Maintainer),
+            &columns[..1],
+            b',',
+            &mut output,
+        )?;
+        assert_eq!(
+            "group,pkgname\n-,xyz\nnuhro,test\norhun,test\n",
+            String::from_utf8(output)?
+        );
+        let series = [StatsSeries {
+            name: String::from("system"),
+            points: vec![StatsPoint {
+                timestamp: 86400,
+                total: 3,
+                reproducible: 2,
+                percentage: 200. / 3.,
+            }],
+        }];
+        let mut output = Vec::new();
+        write_stats_delimited(&series, b',', &mut output)?;
+        assert_eq!(
+            "name,timestamp,date,total,reproducible,percentage\n\
+            system,86400,1970-01-02 00:00:00 UTC,3,2,66.67\n",
+            String::from_utf8(output)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_html() -> Result<()> {
+        let packages = vec![
+            Package {
+                data: ArchwebPackage {
+                    pkgname: String::from("test"),
+                    pkgbase: String::from("test"),
+                    repo: String::from("extra"),
+                    arch: String::from("x86_64"),
+                    pkgver: String::from("0.1"),
+                    pkgrel: String::from("2"),
+                    ..ArchwebPackage::default()
+                },
+                status: Status::Bad,
+                build_id: 42,
+                rebuilderd: String::from("https://reproducible.archlinux.org"),
+                results: vec![
+                    RebuildResult {
+                        rebuilderd: String::from("https://reproducible.archlinux.org"),
+                        status: Status::Bad,
+                        build_id: 42,
+                        artifact_url: String::new(),
+                        has_attestation: false,
+                    },
+                    RebuildResult {
+                        rebuilderd: String::from("https://rebuilder.example.com"),
+                        status: Status::Good,
+                        build_id: 7,
+                        artifact_url: String::new(),
+                        has_attestation: false,
+                    },
+                ],
+                ..Package::default()
+            },
+            Package {
+                data: ArchwebPackage {
+                    pkgname: String::from("<xyz>"),
+                    ..ArchwebPackage::default()
+                },
+                status: Status::Unknown,
+                ..Package::default()
+            },
+        ];
+        let mut output = Vec::new();
+        write_html(
+            &packages,
+            None,
+            Some(GroupBy::Repo),
+            "Packages of <orhun>",
+            &[
+                String::from("https://reproducible.archlinux.org"),
+                String::from("https://rebuilder.example.com"),
+            ],
+            Some(CacheAge::new(1_000_000, 1_000_125)),
+            &mut output,
+        )?;
+        let html = String::from_utf8(output)?;
+        assert!(html.contains("<p>Using cached data from 1970-01-12 13:46:40 UTC (2m 5s old).</p>"));
+        assert!(html.contains("<title>Packages of &lt;orhun&gt;</title>"));
+        assert!(html.contains("0/2 packages are reproducible (<strong>0.00%</strong>)"));
+        assert!(
+            html.contains("<a href=\"https://archlinux.org/packages/extra/x86_64/test/\">test</a>")
+        );
+        assert!(html.contains(
+            "<a href=\"https://reproducible.archlinux.org/api/v0/builds/42/log\">build</a>"
+        ));
+        assert!(html.contains(
+            "<a href=\"https://reproducible.archlinux.org/api/v0/builds/42/diffoscope\">diffoscope</a>"
+        ));
+        assert!(html.contains("<td>-</td><td>&lt;xyz&gt;</td>"));
+        assert!(html.contains("<tr><th>Group</th><th>Name</th>"));
+        assert!(html.contains("<tr data-status=\"BAD\" class=\"disagreement\">"));
+        assert!(html.contains("rebuilder.example.com: <span class=\"GOOD\">GOOD</span>"));
+        assert!(!html.contains("builds/0/"));
+        Ok(())
+    }
+}
@@ -1,17 +1,20 @@
 //! Common package data.
 
 use crate::archweb::ArchwebPackage;
+use crate::cache::{get_cache_dir, get_instance_dir_name, LOGS_DIR, LOG_SUFFIX};
 use crate::error::ReproStatusError;
+use clap::ArgEnum;
 use colored::*;
-use rebuilderd_common::Status;
-use std::env;
+use rebuilderd_common::Status as RebuilderdStatus;
+use std::collections::HashSet;
 use std::fmt;
 use std::fs;
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// Type of logs that rebuilderd provides.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogType {
     /// Build logs.
     Build,
@@ -25,16 +28,278 @@ impl fmt::Display for LogType {
     }
 }
 
+/// Reproducibility status of a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Status {
+    /// Package is reproducible.
+    #[serde(rename = "GOOD")]
+    Good,
+    /// Package is not reproducible.
+    #[serde(rename = "BAD")]
+    Bad,
+    /// Package is not known by rebuilderd.
+    #[serde(rename = "UNKWN")]
+    Unknown,
+    /// Rebuilderd has a different version of the package (e.g. not yet rebuilt).
+    #[serde(rename = "STALE")]
+    Stale,
+}
+
+impl From<RebuilderdStatus> for Status {
+    fn from(status: RebuilderdStatus) -> Self {
+        match status {
+            RebuilderdStatus::Good => Self::Good,
+            RebuilderdStatus::Bad => Self::Bad,
+            RebuilderdStatus::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GOOD" => Ok(Self::Good),
+            "BAD" => Ok(Self::Bad),
+            "UNKWN" => Ok(Self::Unknown),
+            "STALE" => Ok(Self::Stale),
+            _ => Err(format!("unknown status: {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Good => "GOOD",
+            Self::Bad => "BAD",
+            Self::Unknown => "UNKWN",
+            Self::Stale => "STALE",
+        })
+    }
+}
+
+impl Status {
+    /// Returns the colored and padded representation of the status.
+    pub fn fancy(&self) -> String {
+        let status = format!("{:5}", self.to_string());
+        match self {
+            Self::Good => status.green(),
+            Self::Bad => status.red(),
+            Self::Unknown => status.yellow(),
+            Self::Stale => status.blue(),
+        }
+        .to_string()
+    }
+}
+
+/// Result of verifying a local package file against the artifact that rebuilderd checked.
+///
+/// The artifact is the package from the repository that rebuilderd compared
+/// with its rebuild, so it is only known to be reproducible if the build is GOOD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum Verification {
+    /// Package file is identical to the artifact and the build is GOOD.
+    #[serde(rename = "MATCH")]
+    Match,
+    /// Package file is identical to the artifact but the build is not GOOD.
+    #[serde(rename = "UNVERIFIED")]
+    Unverified,
+    /// Package file differs from the artifact.
+    #[serde(rename = "MISMATCH")]
+    Mismatch,
+    /// Package file or the artifact is not available.
+    #[serde(rename = "MISSING")]
+    Missing,
+    /// Package file could not be verified (e.g. the artifact could not be fetched).
+    #[serde(rename = "ERROR")]
+    Error,
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Match => "MATCH",
+            Self::Unverified => "UNVERIFIED",
+            Self::Mismatch => "MISMATCH",
+            Self::Missing => "MISSING",
+            Self::Error => "ERROR",
+        })
+    }
+}
+
+impl Verification {
+    /// Returns the colored representation of the verification result.
+    pub fn fancy(&self) -> String {
+        let verification = self.to_string();
+        match self {
+            Self::Match => verification.green(),
+            Self::Unverified => verification.blue(),
+            Self::Mismatch => verification.red().bold(),
+            Self::Missing => verification.yellow(),
+            Self::Error => verification.red(),
+        }
+        .to_string()
+    }
+}
+
+/// Returns the given rebuilderd URL without the trailing slashes.
+pub fn normalize_rebuilderd_url(url: &str) -> &str {
+    url.trim().trim_end_matches('/')
+}
+
+/// Returns the normalized rebuilderd URLs without duplicates in the given order.
+pub fn dedup_rebuilderd_urls(urls: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    urls.iter()
+        .map(|url| normalize_rebuilderd_url(url))
+        .filter(|url| seen.insert(*url))
+        .map(String::from)
+        .collect()
+}
+
+/// Verdict of the threshold policy for a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Verdict {
+    /// Number of the rebuilderd instances that report GOOD for the checked version.
+    pub confirmations: usize,
+    /// Number of the required confirmations.
+    pub required: usize,
+    /// Whether if the package satisfies the policy.
+    pub passed: bool,
+}
+
+impl Verdict {
+    /// Constructs a new instance from the results of the rebuilderd instances.
+    ///
+    /// Each instance is counted once and only the results of the trusted
+    /// instances are counted if they are given.
+    pub fn new(results: &[RebuildResult], required: usize, trusted: Option<&[String]>) -> Self {
+        let confirmations = results
+            .iter()
+            .filter(|result| result.status == Status::Good)
+            .map(|result| normalize_rebuilderd_url(&result.rebuilderd))
+            .filter(|rebuilderd| {
+                trusted.is_none_or(|trusted| {
+                    trusted
+                        .iter()
+                        .any(|url| normalize_rebuilderd_url(url) == *rebuilderd)
+                })
+            })
+            .collect::<HashSet<&str>>()
+            .len();
+        Self {
+            confirmations,
+            required,
+            passed: confirmations >= required,
+        }
+    }
+
+    /// Returns the colored representation of the verdict.
+    pub fn fancy(&self) -> String {
+        let verdict = format!(
+            "{} {}/{}",
+            if self.passed { "PASS" } else { "FAIL" },
+            self.confirmations,
+            self.required
+        );
+        if self.passed {
+            verdict.green().to_string()
+        } else {
+            verdict.red().to_string()
+        }
+    }
+}
+
+/// Policy for combining the results of multiple rebuilderd instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Policy {
+    /// Package is reproducible if any of the instances reports GOOD.
+    Any,
+    /// Package is reproducible if all of the instances report GOOD.
+    All,
+    /// Package is reproducible if the majority of the instances report GOOD.
+    Majority,
+}
+
+impl Policy {
+    /// Returns the combined status of the given statuses.
+    ///
+    /// If the policy is not satisfied, the status is
+    /// BAD, STALE or UNKWN in the order of precedence.
+    pub fn get_verdict(&self, statuses: &[Status]) -> Status {
+        let good = statuses.iter().filter(|s| **s == Status::Good).count();
+        let is_satisfied = match self {
+            Self::Any => good > 0,
+            Self::All => good == statuses.len(),
+            Self::Majority => good * 2 > statuses.len(),
+        };
+        if is_satisfied && !statuses.is_empty() {
+            Status::Good
+        } else {
+            [Status::Bad, Status::Stale]
+                .into_iter()
+                .find(|status| statuses.contains(status))
+                .unwrap_or(Status::Unknown)
+        }
+    }
+}
+
+/// Result of a rebuilderd instance for a package.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RebuildResult {
+    /// Address of the rebuilderd instance.
+    pub rebuilderd: String,
+    /// Reproducibility status reported by the instance.
+    pub status: Status,
+    /// Rebuilderd build ID.
+    pub build_id: i32,
+    /// URL of the artifact that is rebuilt.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub artifact_url: String,
+    /// Whether if the instance published an attestation for the build.
+    #[serde(skip)]
+    pub has_attestation: bool,
+}
+
+impl RebuildResult {
+    /// Returns the host name of the rebuilderd instance.
+    pub fn host(&self) -> String {
+        reqwest::Url::parse(&self.rebuilderd)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_else(|| self.rebuilderd.to_string())
+    }
+}
+
 /// Package that consists of archweb data
 /// and essential information from rebuilderd.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Package {
     /// Package data from the Arch Linux website.
     pub data: ArchwebPackage,
     /// Reproducibility status of the package.
+    ///
+    /// This is the combined status in case of multiple rebuilderd instances.
     pub status: Status,
     /// Rebuilderd build ID.
     pub build_id: i32,
+    /// Address of the rebuilderd instance that the build ID belongs to.
+    pub rebuilderd: String,
+    /// Results of each rebuilderd instance.
+    pub results: Vec<RebuildResult>,
+    /// Result of verifying the local package file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Verification>,
+    /// Number of the trusted rebuilders that signed an attestation for the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attested_by: Option<usize>,
+    /// Verdict of the threshold policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<Verdict>,
+    /// SHA-256 checksum of the package file that is published in the repository database.
+    #[serde(skip)]
+    pub published_checksum: Option<String>,
 }
 
 impl Default for Package {
@@ -43,22 +308,19 @@ impl Default for Package {
             data: ArchwebPackage::default(),
             status: Status::Unknown,
             build_id: 0,
+            rebuilderd: String::new(),
+            results: Vec::new(),
+            verification: None,
+            attested_by: None,
+            verdict: None,
+            published_checksum: None,
         }
     }
 }
 
 impl fmt::Display for Package {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let version = format!(
-            "{}{}-{}",
-            if self.data.epoch != 0 {
-                format!("{}:", self.data.epoch)
-            } else {
-                String::new()
-            },
-            self.data.pkgver,
-            self.data.pkgrel
-        );
+        let version = self.data.version();
         write!(
             f,
             "{} {} {}",
@@ -69,21 +331,107 @@ impl fmt::Display for Package {
                 version.normal()
             },
             self.status.fancy()
-        )
+        )?;
+        if self.has_disagreement() {
+            write!(
+                f,
+                " ({})",
+                self.results
+                    .iter()
+                    .map(|result| format!(
+                        "{}: {}",
+                        result.host(),
+                        result.status.fancy().trim_end()
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?;
+        }
+        if let Some(verification) = self.verification {
+            write!(f, " {}", verification.fancy())?;
+        }
+        if let Some(verdict) = self.verdict {
+            write!(f, " [{}]", verdict.fancy())?;
+        }
+        if let Some(attested_by) = self.attested_by {
+            write!(
+                f,
+                " {}",
+                format!(
+                    "(attested by {} trusted rebuilder{})",
+                    attested_by,
+                    if attested_by == 1 { "" } else { "s" }
+                )
+                .dimmed()
+            )?;
+        }
+        Ok(())
     }
 }
 
 impl Package {
-    /// Returns the path to save logs based on the log type and build ID.
+    /// Constructs a new instance from the results of the rebuilderd instances.
+    ///
+    /// The build ID is taken from the first instance that knows the package.
+    pub fn new(data: ArchwebPackage, results: Vec<RebuildResult>, policy: Policy) -> Self {
+        let statuses = results
+            .iter()
+            .map(|result| result.status)
+            .collect::<Vec<Status>>();
+        let primary = results.iter().find(|result| result.build_id != 0);
+        Self {
+            data,
+            status: policy.get_verdict(&statuses),
+            build_id: primary.map(|result| result.build_id).unwrap_or_default(),
+            rebuilderd: primary
+                .map(|result| result.rebuilderd.to_string())
+                .unwrap_or_default(),
+            results,
+            verification: None,
+            attested_by: None,
+            verdict: None,
+            published_checksum: None,
+        }
+    }
+
+    /// Returns the result that has the artifact checked by the rebuilderd instances.
+    ///
+    /// The GOOD results are preferred.
+    pub fn artifact_result(&self) -> Option<&RebuildResult> {
+        let mut results = self
+            .results
+            .iter()
+            .filter(|result| !result.artifact_url.is_empty());
+        results
+            .clone()
+            .find(|result| result.status == Status::Good)
+            .or_else(|| results.next())
+    }
+
+    /// Returns the URL of the artifact that is checked by the rebuilderd instances.
+    pub fn artifact_url(&self) -> Option<&str> {
+        self.artifact_result()
+            .map(|result| result.artifact_url.as_str())
+    }
+
+    /// Returns `true` if the rebuilderd instances report different statuses.
+    pub fn has_disagreement(&self) -> bool {
+        self.results
+            .windows(2)
+            .any(|results| results[0].status != results[1].status)
+    }
+
+    /// Returns the path to save logs based on the rebuilderd instance,
+    /// log type and build ID.
     pub fn get_log_path(
         &self,
         log_type: LogType,
         cache_dir: Option<PathBuf>,
     ) -> Result<PathBuf, ReproStatusError> {
-        let path = cache_dir
-            .or_else(|| dirs_next::cache_dir().map(|p| p.join(env!("CARGO_PKG_NAME"))))
-            .ok_or_else(|| IoError::new(IoErrorKind::Other, "cannot find cache directory"))?
-            .join(format!("{}_{}.log", self.build_id, log_type,));
+        let path = get_cache_dir(cache_dir)?
+            .join(LOGS_DIR)
+            .join(get_instance_dir_name(&self.rebuilderd))
+            .join(format!("{}_{}{}", self.build_id, log_type, LOG_SUFFIX));
         if !path.exists() {
             fs::create_dir_all(match path.parent() {
                 Some(parent) => parent,
@@ -110,10 +458,77 @@ mod tests {
                 ..ArchwebPackage::default()
             },
             status: Status::Good,
-            build_id: 0,
+            build_id: 42,
+            rebuilderd: String::from("https://reproducible.archlinux.org"),
+            ..Package::default()
         };
         let path = package.get_log_path(LogType::Diffoscope, Some(PathBuf::from("test")))?;
-        assert_eq!("test/0_diffoscope.log", path.to_string_lossy());
+        assert_eq!(
+            "test/logs/reproducible.archlinux.org/42_diffoscope.log.zst",
+            path.to_string_lossy()
+        );
         Ok(())
     }
+
+    #[test]
+    fn test_package_results() {
+        let results = [
+            ("https://reproducible.archlinux.org", Status::Good, 1),
+            ("http://rebuilder.example.com:8484/", Status::Bad, 2),
+            ("https://rebuilder.invalid", Status::Good, 3),
+        ]
+        .into_iter()
+        .map(|(rebuilderd, status, build_id)| RebuildResult {
+            rebuilderd: rebuilderd.to_string(),
+            status,
+            build_id,
+            artifact_url: String::new(),
+            has_attestation: false,
+        })
+        .collect::<Vec<RebuildResult>>();
+        let package = Package::new(ArchwebPackage::default(), results.clone(), Policy::All);
+        assert_eq!(Status::Bad, package.status);
+        assert_eq!(1, package.build_id);
+        assert_eq!("https://reproducible.archlinux.org", package.rebuilderd);
+        assert!(package.has_disagreement());
+        assert_eq!("rebuilder.example.com", package.results[1].host());
+        let package = Package::new(ArchwebPackage::default(), results.clone(), Policy::Any);
+        assert_eq!(Status::Good, package.status);
+        let package = Package::new(ArchwebPackage::default(), results.clone(), Policy::Majority);
+        assert_eq!(Status::Good, package.status);
+        assert_eq!(
+            Status::Stale,
+            Policy::Majority.get_verdict(&[Status::Good, Status::Stale, Status::Unknown])
+        );
+        assert_eq!(Status::Unknown, Policy::Any.get_verdict(&[]));
+        let verdict = Verdict::new(&results, 2, None);
+        assert_eq!(2, verdict.confirmations);
+        assert!(verdict.passed);
+        let trusted = [
+            String::from("https://reproducible.archlinux.org/"),
+            String::from("http://rebuilder.example.com:8484/"),
+        ];
+        let verdict = Verdict::new(&results, 2, Some(&trusted));
+        assert_eq!(1, verdict.confirmations);
+        assert!(!verdict.passed);
+        let duplicated = [&results[0], &results[0]]
+            .into_iter()
+            .enumerate()
+            .map(|(i, result)| RebuildResult {
+                rebuilderd: format!("{}{}", result.rebuilderd, "/".repeat(i)),
+                ..result.clone()
+            })
+            .collect::<Vec<RebuildResult>>();
+        let verdict = Verdict::new(&duplicated, 2, None);
+        assert_eq!(1, verdict.confirmations);
+        assert!(!verdict.passed);
+        assert_eq!(
+            vec!["https://x.org", "https://y.org"],
+            dedup_rebuilderd_urls(&[
+                String::from("https://x.org"),
+                String::from("https://y.org/"),
+                String::from("https://x.org/"),
+            ])
+        );
+    }
 }
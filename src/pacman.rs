@@ -0,0 +1,380 @@
+//! Parser for the pacman configuration file.
+
+use crate::error::ReproStatusError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default path of the pacman configuration file.
+pub const PACMAN_CONF: &str = "/etc/pacman.conf";
+
+/// Default root directory.
+pub const DEFAULT_ROOT_DIR: &str = "/";
+
+/// Default path of the pacman database.
+pub const DEFAULT_DB_PATH: &str = "/var/lib/pacman/";
+
+/// Default package cache directory.
+pub const DEFAULT_CACHE_DIR: &str = "/var/cache/pacman/pkg/";
+
+/// Default sync repositories.
+pub const DEFAULT_REPOS: &[&str] = &["core", "extra", "multilib"];
+
+/// Maximum depth of the nested `Include` directives.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Settings from the pacman configuration file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PacmanConfig {
+    /// Value of the `RootDir` option.
+    pub root_dir: Option<String>,
+    /// Value of the `DBPath` option.
+    pub db_path: Option<String>,
+    /// Values of the `CacheDir` option.
+    pub cache_dirs: Vec<String>,
+    /// Enabled sync repositories in the order of appearance.
+    pub repos: Vec<String>,
+}
+
+/// Options for initializing alpm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlpmOptions {
+    /// Root directory.
+    pub root_dir: String,
+    /// Path of the database.
+    pub db_path: String,
+    /// Sync repositories to register.
+    pub repos: Vec<String>,
+}
+
+/// Returns the given path inside the root directory.
+fn join_root(root: &Path, path: &str) -> String {
+    root.join(path.trim_start_matches('/'))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns the path of the pacman configuration file.
+///
+/// If an alternate root is given, the configuration file inside it
+/// is preferred over the default one.
+pub fn get_config_path(path: Option<PathBuf>, root: Option<&Path>) -> PathBuf {
+    path.unwrap_or_else(|| {
+        root.map(|root| PathBuf::from(join_root(root, PACMAN_CONF)))
+            .filter(|path| path.exists())
+            .unwrap_or_else(|| PathBuf::from(PACMAN_CONF))
+    })
+}
+
+/// Returns `true` if the given file name matches the glob pattern.
+///
+/// Only the `*` wildcard is supported.
+fn matches_glob(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    if !name.starts_with(first) {
+        return false;
+    }
+    let mut rest = &name[first.len()..];
+    let parts = parts.collect::<Vec<&str>>();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+/// Returns the paths that the given `Include` value points to.
+fn get_include_paths(value: &str) -> Vec<PathBuf> {
+    let path = Path::new(value);
+    match (
+        path.parent(),
+        path.file_name().and_then(|name| name.to_str()),
+    ) {
+        (Some(parent), Some(pattern)) if pattern.contains('*') => {
+            let mut paths = fs::read_dir(parent)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| {
+                            path.file_name()
+                                .and_then(|name| name.to_str())
+                                .is_some_and(|name| matches_glob(pattern, name))
+                        })
+                        .collect::<Vec<PathBuf>>()
+                })
+                .unwrap_or_default();
+            paths.sort();
+            paths
+        }
+        _ => vec![path.to_path_buf()],
+    }
+}
+
+impl PacmanConfig {
+    /// Parses the pacman configuration file at the given path.
+    ///
+    /// If a root directory is given, the `Include` paths are resolved inside of it.
+    pub fn load(path: &Path, root: Option<&Path>) -> Result<Self, ReproStatusError> {
+        log::debug!("parsing pacman configuration: {:?}", path);
+        let mut config = Self::default();
+        let mut section = None;
+        config.parse(path, root, &mut section, 0)?;
+        Ok(config)
+    }
+
+    /// Returns the alpm options with the given overrides applied.
+    ///
+    /// If an alternate root is given, the database path is derived from it.
+    /// Otherwise, the default database path is derived from `RootDir` (as pacman does)
+    /// unless `DBPath` is set.
+    pub fn get_alpm_options(
+        &self,
+        root: Option<&Path>,
+        db_path: Option<&str>,
+        repos: Option<Vec<String>>,
+    ) -> AlpmOptions {
+        let db_path = db_path.map(String::from).unwrap_or_else(|| {
+            match (root, self.db_path.as_deref(), self.root_dir.as_deref()) {
+                (Some(root), db_path, _) => join_root(root, db_path.unwrap_or(DEFAULT_DB_PATH)),
+                (None, Some(db_path), _) => db_path.to_string(),
+                (None, None, Some(root_dir)) => join_root(Path::new(root_dir), DEFAULT_DB_PATH),
+                (None, None, None) => DEFAULT_DB_PATH.to_string(),
+            }
+        });
+        AlpmOptions {
+            root_dir: match root {
+                Some(root) => root.to_string_lossy().to_string(),
+                None => self
+                    .root_dir
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_ROOT_DIR.to_string()),
+            },
+            db_path,
+            repos: repos.unwrap_or_else(|| {
+                if self.repos.is_empty() {
+                    DEFAULT_REPOS.iter().map(|v| v.to_string()).collect()
+                } else {
+                    self.repos.clone()
+                }
+            }),
+        }
+    }
+
+    /// Returns the package cache directories.
+    ///
+    /// If an alternate root is given, the directories are inside of it.
+    pub fn get_cache_dirs(&self, root: Option<&Path>) -> Vec<PathBuf> {
+        let cache_dirs = if self.cache_dirs.is_empty() {
+            vec![DEFAULT_CACHE_DIR.to_string()]
+        } else {
+            self.cache_dirs.clone()
+        };
+        cache_dirs
+            .iter()
+            .map(|dir| match root {
+                Some(root) => PathBuf::from(join_root(root, dir)),
+                None => PathBuf::from(dir),
+            })
+            .collect()
+    }
+
+    /// Parses the given file and updates the current section.
+    fn parse(
+        &mut self,
+        path: &Path,
+        root: Option<&Path>,
+        section: &mut Option<String>,
+        depth: usize,
+    ) -> Result<(), ReproStatusError> {
+        if depth > MAX_INCLUDE_DEPTH {
+            log::warn!("ignoring deeply nested include: {:?}", path);
+            return Ok(());
+        }
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                let name = name.trim().to_string();
+                if name != "options" && !self.repos.contains(&name) {
+                    self.repos.push(name.clone());
+                }
+                *section = Some(name);
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (line, ""),
+            };
+            match (section.as_deref(), key) {
+                (_, "Include") => {
+                    let value = match root {
+                        Some(root) => join_root(root, value),
+                        None => value.to_string(),
+                    };
+                    for include in get_include_paths(&value) {
+                        if let Err(e) = self.parse(&include, root, section, depth + 1) {
+                            log::warn!("failed to include {:?}: {}", include, e);
+                        }
+                    }
+                }
+                (Some("options"), "RootDir") => self.root_dir = Some(value.to_string()),
+                (Some("options"), "DBPath") => self.db_path = Some(value.to_string()),
+                (Some("options"), "CacheDir") => self
+                    .cache_dirs
+                    .extend(value.split_whitespace().map(String::from)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_pacman_config() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let dir = temp_dir.path().to_path_buf();
+        fs::create_dir_all(dir.join("repos.d"))?;
+        fs::write(
+            dir.join("pacman.conf"),
+            format!(
+                "# comment\n\
+                [options]\n\
+                RootDir = /mnt\n\
+                DBPath  = /mnt/var/lib/pacman/\n\
+                CacheDir = /var/cache/pacman/pkg/ /tmp/pkg/\n\
+                CheckSpace\n\
+                \n\
+                [core-testing]\n\
+                Include = {0}/mirrorlist\n\
+                \n\
+                [core]\n\
+                Include = {0}/mirrorlist\n\
+                \n\
+                Include = {0}/repos.d/*.conf\n",
+                dir.to_string_lossy()
+            ),
+        )?;
+        fs::write(
+            dir.join("mirrorlist"),
+            "Server = https://geo.mirror.pkgbuild.com/$repo/os/$arch\n",
+        )?;
+        fs::write(
+            dir.join("repos.d").join("custom.conf"),
+            "[custom]\nServer = file:///srv\n",
+        )?;
+        fs::write(dir.join("repos.d").join("ignored.txt"), "[ignored]\n")?;
+        let config = PacmanConfig::load(&dir.join("pacman.conf"), None)?;
+        assert_eq!(
+            PacmanConfig {
+                root_dir: Some(String::from("/mnt")),
+                db_path: Some(String::from("/mnt/var/lib/pacman/")),
+                cache_dirs: vec![
+                    String::from("/var/cache/pacman/pkg/"),
+                    String::from("/tmp/pkg/")
+                ],
+                repos: vec![
+                    String::from("core-testing"),
+                    String::from("core"),
+                    String::from("custom")
+                ],
+            },
+            config
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_alpm_options() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path().to_path_buf();
+        assert_eq!(
+            PathBuf::from(PACMAN_CONF),
+            get_config_path(None, Some(&root))
+        );
+        fs::create_dir_all(root.join("etc"))?;
+        fs::write(
+            root.join("etc").join("pacman.conf"),
+            "[core]\nInclude = /etc/pacman.d/repos.conf\n",
+        )?;
+        fs::create_dir_all(root.join("etc").join("pacman.d"))?;
+        fs::write(
+            root.join("etc").join("pacman.d").join("repos.conf"),
+            "[chroot]\n",
+        )?;
+        assert_eq!(
+            root.join("etc").join("pacman.conf"),
+            get_config_path(None, Some(&root))
+        );
+        assert_eq!(
+            PathBuf::from("test.conf"),
+            get_config_path(Some(PathBuf::from("test.conf")), Some(&root))
+        );
+        let config = PacmanConfig::load(&get_config_path(None, Some(&root)), Some(&root))?;
+        assert_eq!(
+            AlpmOptions {
+                root_dir: root.to_string_lossy().to_string(),
+                db_path: root.join("var/lib/pacman/").to_string_lossy().to_string(),
+                repos: vec![String::from("core"), String::from("chroot")],
+            },
+            config.get_alpm_options(Some(&root), None, None)
+        );
+        assert_eq!(
+            AlpmOptions {
+                root_dir: String::from("/"),
+                db_path: String::from("/tmp/db"),
+                repos: vec![String::from("extra")],
+            },
+            config.get_alpm_options(None, Some("/tmp/db"), Some(vec![String::from("extra")]))
+        );
+        assert_eq!(
+            vec![root.join("var/cache/pacman/pkg/")],
+            config.get_cache_dirs(Some(&root))
+        );
+        assert_eq!(
+            AlpmOptions {
+                root_dir: String::from("/mnt"),
+                db_path: String::from("/mnt/var/lib/pacman/"),
+                repos: vec![String::from("core"), String::from("chroot")],
+            },
+            PacmanConfig {
+                root_dir: Some(String::from("/mnt")),
+                ..config.clone()
+            }
+            .get_alpm_options(None, None, None)
+        );
+        assert_eq!(
+            vec!["core", "extra", "multilib"],
+            PacmanConfig::default()
+                .get_alpm_options(None, None, None)
+                .repos
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches_glob() {
+        assert!(matches_glob("*.conf", "custom.conf"));
+        assert!(matches_glob("repo-*.conf", "repo-x.conf"));
+        assert!(matches_glob("*", "anything"));
+        assert!(matches_glob("exact", "exact"));
+        assert!(!matches_glob("*.conf", "custom.txt"));
+        assert!(!matches_glob("repo-*.conf", "other-x.conf"));
+    }
+}
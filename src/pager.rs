@@ -0,0 +1,141 @@
+//! Pager for viewing the logs.
+
+use crate::error::ReproStatusError;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+/// Interval of checking the temporary file for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Pager process that reads from its standard input.
+///
+/// The written data is stored in an uncompressed temporary file that is
+/// followed on a separate thread and written to the pager as it reads.
+/// So writing never blocks on the pager and the pager receives all of the data.
+pub struct Pager {
+    /// Child process of the pager.
+    child: Child,
+    /// Temporary file that holds the written data.
+    file: NamedTempFile,
+    /// Whether if all of the data is written.
+    is_finished: Arc<AtomicBool>,
+    /// Thread that writes to the standard input of the pager.
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Writes the data of the given file to the standard input of the pager
+/// as it grows until the file is finished or the pager stops reading.
+fn follow_file(mut file: File, mut stdin: ChildStdin, is_finished: &AtomicBool) {
+    let mut buffer = [0; 8192];
+    loop {
+        let is_last = is_finished.load(Ordering::Acquire);
+        match file.read(&mut buffer) {
+            Ok(0) if is_last => break,
+            Ok(0) => thread::sleep(POLL_INTERVAL),
+            Ok(size) => {
+                if stdin.write_all(&buffer[..size]).is_err() {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+impl Pager {
+    /// Spawns the given pager.
+    pub fn spawn(pager: &str) -> Result<Self, ReproStatusError> {
+        Self::spawn_command(Command::new(pager))
+    }
+
+    /// Spawns the pager from the given command.
+    fn spawn_command(mut command: Command) -> Result<Self, ReproStatusError> {
+        let file = NamedTempFile::new()?;
+        let reader = file.reopen()?;
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        let is_finished = Arc::new(AtomicBool::new(false));
+        let thread = child.stdin.take().map(|stdin| {
+            let is_finished = Arc::clone(&is_finished);
+            thread::spawn(move || follow_file(reader, stdin, &is_finished))
+        });
+        Ok(Self {
+            child,
+            file,
+            is_finished,
+            thread,
+        })
+    }
+
+    /// Returns `true` if the pager has exited.
+    pub fn has_exited(&mut self) -> Result<bool, ReproStatusError> {
+        Ok(self.child.try_wait()?.is_some())
+    }
+
+    /// Marks the data as complete so that the standard input of the pager
+    /// is closed once all of it is written.
+    pub fn finish(&mut self) -> Result<(), ReproStatusError> {
+        self.file.flush()?;
+        self.is_finished.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Finishes the data and waits for the pager to exit.
+    pub fn wait(mut self) -> Result<(), ReproStatusError> {
+        self.finish()?;
+        if let Some(thread) = self.thread.take() {
+            thread.join().map_err(|_| ReproStatusError::Unknown)?;
+        }
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn test_pager() -> Result<()> {
+        let mut pager = Pager::spawn("true")?;
+        while !pager.has_exited()? {
+            thread::sleep(POLL_INTERVAL);
+        }
+        pager.write_all(b"test")?;
+        pager.wait()?;
+        assert!(Pager::spawn("xyz-pager").is_err());
+        let output = tempfile::NamedTempFile::new()?;
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            "sleep 0.5; cat > \"$0\"",
+            &output.path().to_string_lossy(),
+        ]);
+        let mut pager = Pager::spawn_command(command)?;
+        let chunk = vec![b'x'; 65536];
+        for _ in 0..64 {
+            pager.write_all(&chunk)?;
+        }
+        pager.wait()?;
+        assert_eq!(64 * 65536, fs::read(output.path())?.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+//! Statistics of the recorded results over time.
+
+use crate::history::HistoryRecord;
+use crate::package::Status;
+use clap::ArgEnum;
+use std::collections::BTreeMap;
+
+/// Characters of the sparkline from the lowest to the highest value.
+const SPARKLINE_TICKS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Grouping of the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum StatsGroup {
+    /// Checked set of packages (e.g. the system or a maintainer).
+    Target,
+    /// Maintainer of the packages.
+    Maintainer,
+    /// Repository of the packages.
+    Repo,
+}
+
+/// Reproducibility of a group of packages in a run.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct StatsPoint {
+    /// Unix timestamp of the run.
+    pub timestamp: i64,
+    /// Total number of packages.
+    pub total: usize,
+    /// Number of reproducible packages.
+    pub reproducible: usize,
+    /// Percentage of the reproducible packages.
+    pub percentage: f64,
+}
+
+/// Reproducibility of a group of packages over time.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StatsSeries {
+    /// Name of the group.
+    pub name: String,
+    /// Results of each run in chronological order.
+    pub points: Vec<StatsPoint>,
+}
+
+impl StatsSeries {
+    /// Returns the percentages of the runs.
+    pub fn percentages(&self) -> Vec<f64> {
+        self.points.iter().map(|point| point.percentage).collect()
+    }
+}
+
+/// Returns the series of the given records.
+///
+/// Each record is a single point of the target if the group is not given.
+/// Packages without a maintainer or repository are not counted
+/// while grouping by them.
+pub fn get_series(records: &[HistoryRecord], group: Option<StatsGroup>) -> Vec<StatsSeries> {
+    let mut series = BTreeMap::<String, Vec<StatsPoint>>::new();
+    for record in records {
+        let mut counts = BTreeMap::<&str, (usize, usize)>::new();
+        for pkg in &record.packages {
+            let keys = match group {
+                None | Some(StatsGroup::Target) => vec![record.target.as_str()],
+                Some(StatsGroup::Maintainer) => {
+                    pkg.maintainers.iter().map(String::as_str).collect()
+                }
+                Some(StatsGroup::Repo) if pkg.repo.is_empty() => Vec::new(),
+                Some(StatsGroup::Repo) => vec![pkg.repo.as_str()],
+            };
+            for key in keys {
+                let count = counts.entry(key).or_default();
+                count.0 += 1;
+                if pkg.status == Status::Good {
+                    count.1 += 1;
+                }
+            }
+        }
+        for (key, (total, reproducible)) in counts {
+            series.entry(key.to_string()).or_default().push(StatsPoint {
+                timestamp: record.timestamp,
+                total,
+                reproducible,
+                percentage: (reproducible as f64 / total as f64) * 100.,
+            });
+        }
+    }
+    series
+        .into_iter()
+        .map(|(name, points)| StatsSeries { name, points })
+        .collect()
+}
+
+/// Returns the sparkline of the given values.
+///
+/// The values are scaled between the minimum and the maximum.
+pub fn get_sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            let index = if max > min {
+                ((value - min) / (max - min) * (SPARKLINE_TICKS.len() - 1) as f64).round() as usize
+            } else {
+                SPARKLINE_TICKS.len() / 2
+            };
+            SPARKLINE_TICKS[index]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryEntry;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get_series() {
+        let records = [
+            (100, [("core", Status::Good), ("extra", Status::Bad)]),
+            (200, [("core", Status::Good), ("extra", Status::Good)]),
+        ]
+        .into_iter()
+        .map(|(timestamp, packages)| HistoryRecord {
+            timestamp,
+            target: String::from("system"),
+            packages: packages
+                .into_iter()
+                .map(|(repo, status)| HistoryEntry {
+                    name: String::from("test"),
+                    version: String::from("0.1-1"),
+                    status,
+                    build_id: 0,
+                    repo: repo.to_string(),
+                    maintainers: vec![String::from("orhun")],
+                })
+                .collect(),
+        })
+        .collect::<Vec<HistoryRecord>>();
+        let series = get_series(&records, None);
+        assert_eq!(1, series.len());
+        assert_eq!("system", series[0].name);
+        assert_eq!(vec![50., 100.], series[0].percentages());
+        assert_eq!(2, series[0].points[1].total);
+        let series = get_series(&records, Some(StatsGroup::Repo));
+        assert_eq!(
+            vec![("core", vec![100., 100.]), ("extra", vec![0., 100.])],
+            series
+                .iter()
+                .map(|series| (series.name.as_str(), series.percentages()))
+                .collect::<Vec<(&str, Vec<f64>)>>()
+        );
+        let series = get_series(&records, Some(StatsGroup::Maintainer));
+        assert_eq!("orhun", series[0].name);
+        assert_eq!(vec![50., 100.], series[0].percentages());
+    }
+
+    #[test]
+    fn test_get_sparkline() {
+        assert_eq!("▁▅█", get_sparkline(&[80., 85., 90.]));
+        assert_eq!("▅▅", get_sparkline(&[50., 50.]));
+        assert_eq!("", get_sparkline(&[]));
+    }
+}
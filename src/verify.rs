@@ -0,0 +1,235 @@
+//! Verification of the local package files.
+
+use crate::archweb::ArchwebPackage;
+use crate::cache::{CachedResponse, HttpCache};
+use crate::error::ReproStatusError;
+use crate::fetch::{fetch_artifact_checksum, fetch_rebuilderd_attestation};
+use crate::package::{Package, RebuildResult, Status, Verification};
+use reqwest::Client as HttpClient;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns the prefix of the file name of the given package (`name-version-arch.pkg.tar`).
+pub fn get_package_file_prefix(data: &ArchwebPackage) -> String {
+    format!("{}-{}-{}.pkg.tar", data.pkgname, data.version(), data.arch)
+}
+
+/// Returns the path of the package file in the given cache directories.
+///
+/// Signature files are ignored.
+pub fn find_package_file(cache_dirs: &[PathBuf], data: &ArchwebPackage) -> Option<PathBuf> {
+    let prefix = get_package_file_prefix(data);
+    cache_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix) && !name.ends_with(".sig"))
+        })
+}
+
+/// Returns the SHA-256 checksum of the given file.
+pub fn get_file_checksum(path: &Path) -> Result<String, ReproStatusError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the SHA-256 checksum of the artifact at the given URL.
+///
+/// The artifact is only downloaded if its checksum is not cached yet. The cached
+/// checksums are used regardless of their age since the artifacts do not change.
+pub async fn get_cached_artifact_checksum<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    url: &'a str,
+) -> Result<String, ReproStatusError> {
+    let key = format!("{}.sha256", url);
+    if let Some(response) = cache.read(&key) {
+        return Ok(response.body);
+    }
+    if cache.offline {
+        return Err(ReproStatusError::CacheMissError(url.to_string()));
+    }
+    let checksum = fetch_artifact_checksum(client, url).await?;
+    cache.write(&CachedResponse::new(&key, checksum.to_string(), None, None))?;
+    Ok(checksum)
+}
+
+/// Returns the SHA-256 checksum of the artifact of the given result.
+///
+/// The checksums that are published in the repository database or in the
+/// attestation of a GOOD build are preferred over downloading the artifact.
+pub async fn get_artifact_checksum<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    package: &'a Package,
+    result: &'a RebuildResult,
+) -> Result<String, ReproStatusError> {
+    if let Some(checksum) = &package.published_checksum {
+        return Ok(checksum.to_string());
+    }
+    if result.status == Status::Good && result.has_attestation {
+        match fetch_rebuilderd_attestation(client, cache, &result.rebuilderd, result.build_id).await
+        {
+            Ok(attestation) => {
+                if let Some(checksum) =
+                    attestation.get_product_checksum(&get_package_file_prefix(&package.data))
+                {
+                    return Ok(checksum.to_string());
+                }
+            }
+            Err(e) => log::debug!("no attestation for {}: {}", package.data.pkgname, e),
+        }
+    }
+    get_cached_artifact_checksum(client, cache, &result.artifact_url).await
+}
+
+/// Returns the SHA-256 checksum of the given package.
+///
+/// The local package file is used if it is found. Otherwise, the checksum of the artifact is used.
+pub async fn get_package_checksum<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    package: &'a Package,
+    cache_dirs: &'a [PathBuf],
+) -> Result<String, ReproStatusError> {
+    match (
+        find_package_file(cache_dirs, &package.data),
+        package.artifact_result(),
+    ) {
+        (Some(path), _) => get_file_checksum(&path),
+        (None, Some(result)) => get_artifact_checksum(client, cache, package, result).await,
+        (None, None) => Err(ReproStatusError::AttestationError(String::from(
+            "no package file or artifact to compare",
+        ))),
+    }
+}
+
+/// Returns the verification result of the given checksums and status of the build.
+///
+/// The artifact is the package from the repository that rebuilderd compared
+/// with its rebuild, so a matching file is only verified if the build is GOOD.
+pub fn get_verification(checksum: &str, artifact_checksum: &str, status: Status) -> Verification {
+    if checksum != artifact_checksum {
+        Verification::Mismatch
+    } else if status == Status::Good {
+        Verification::Match
+    } else {
+        Verification::Unverified
+    }
+}
+
+/// Verifies the local package file against the artifact that is checked by rebuilderd.
+///
+/// The checksum of the artifact is only looked up if the package file is found.
+pub async fn verify_package<'a>(
+    client: &'a HttpClient,
+    cache: &'a HttpCache,
+    package: &'a Package,
+    cache_dirs: &'a [PathBuf],
+) -> Result<Verification, ReproStatusError> {
+    let (path, result) = match (
+        find_package_file(cache_dirs, &package.data),
+        package.artifact_result(),
+    ) {
+        (Some(path), Some(result)) => (path, result),
+        _ => return Ok(Verification::Missing),
+    };
+    log::debug!("verifying {:?} against {}", path, result.artifact_url);
+    let checksum = get_file_checksum(&path)?;
+    Ok(get_verification(
+        &checksum,
+        &get_artifact_checksum(client, cache, package, result).await?,
+        result.status,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_artifact_checksum() -> Result<()> {
+        let client = HttpClient::new();
+        let cache_dir = tempfile::tempdir()?;
+        let cache = HttpCache::new(cache_dir.path().to_path_buf(), Duration::ZERO, true);
+        let result = RebuildResult {
+            rebuilderd: String::from("https://rebuilderd.invalid"),
+            status: Status::Good,
+            build_id: 1,
+            artifact_url: String::from("https://rebuilderd.invalid/test-0.1-1-x86_64.pkg.tar.zst"),
+            has_attestation: false,
+        };
+        let mut package = Package {
+            results: vec![result.clone()],
+            published_checksum: Some(String::from("abc")),
+            ..Package::default()
+        };
+        assert_eq!(
+            "abc",
+            get_artifact_checksum(&client, &cache, &package, &result).await?
+        );
+        package.published_checksum = None;
+        assert!(matches!(
+            get_artifact_checksum(&client, &cache, &package, &result).await,
+            Err(ReproStatusError::CacheMissError(_))
+        ));
+        cache.write(&CachedResponse::new(
+            &format!("{}.sha256", result.artifact_url),
+            String::from("xyz"),
+            None,
+            None,
+        ))?;
+        assert_eq!(
+            "xyz",
+            get_artifact_checksum(&client, &cache, &package, &result).await?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_package_file() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let cache_dir = temp_dir.path().to_path_buf();
+        let path = cache_dir.join("test-0.1-1-x86_64.pkg.tar.zst");
+        fs::write(&path, "test")?;
+        fs::write(cache_dir.join("test-0.1-1-x86_64.pkg.tar.zst.sig"), "")?;
+        fs::write(cache_dir.join("test-0.1-10-x86_64.pkg.tar.zst"), "")?;
+        let cache_dirs = vec![PathBuf::from("nonexistent"), cache_dir.clone()];
+        let mut data = ArchwebPackage {
+            pkgname: String::from("test"),
+            pkgver: String::from("0.1-1"),
+            arch: String::from("x86_64"),
+            ..ArchwebPackage::default()
+        };
+        assert_eq!(Some(path.clone()), find_package_file(&cache_dirs, &data));
+        assert_eq!(
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+            get_file_checksum(&path)?
+        );
+        data.pkgver = String::from("0.2-1");
+        assert_eq!(None, find_package_file(&cache_dirs, &data));
+        assert_eq!(
+            Verification::Match,
+            get_verification("a", "a", Status::Good)
+        );
+        assert_eq!(
+            Verification::Unverified,
+            get_verification("a", "a", Status::Bad)
+        );
+        assert_eq!(
+            Verification::Mismatch,
+            get_verification("a", "b", Status::Good)
+        );
+        Ok(())
+    }
+}